@@ -0,0 +1,237 @@
+//! Derive macros for `cc-utils`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Fields, LitInt, LitStr, parse_macro_input};
+
+/// Declaration parsed from `#[response(...)]` attribute.
+#[derive(Clone, Default)]
+struct Declaration {
+  status: Option<u16>,
+  public: bool,
+  message: Option<String>,
+  code: Option<String>,
+}
+
+impl Declaration {
+  /// Parses `#[response(status = 404, public, message = "...", code = "...")]` over the inherited declaration.
+  fn parse(attrs: &[Attribute], inherited: &Declaration) -> syn::Result<Self> {
+    let mut declaration = inherited.clone();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("response")) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("status") {
+          let status = meta.value()?.parse::<LitInt>()?;
+          let code = status.base10_parse::<u16>()?;
+          if !(400..=599).contains(&code) {
+            return Err(syn::Error::new(status.span(), "error status must be in 400..=599"));
+          }
+          declaration.status = Some(code);
+        } else if meta.path.is_ident("public") {
+          declaration.public = true;
+        } else if meta.path.is_ident("message") {
+          declaration.message = Some(meta.value()?.parse::<LitStr>()?.value());
+        } else if meta.path.is_ident("code") {
+          declaration.code = Some(meta.value()?.parse::<LitStr>()?.value());
+        } else {
+          return Err(meta.error("expected `status`, `public`, `message` or `code`"));
+        }
+        Ok(())
+      })?;
+    }
+    Ok(declaration)
+  }
+
+  fn status(&self) -> u16 {
+    self.status.unwrap_or(500)
+  }
+
+  fn tokens(&self) -> TokenStream2 {
+    let status = self.status();
+    let public = self.public;
+    let message = match &self.message {
+      Some(message) => quote! { Some(#message) },
+      None => quote! { None },
+    };
+    let code = match &self.code {
+      Some(code) => quote! { Some(#code) },
+      None => quote! { None },
+    };
+    quote! {
+      ::cc_utils::errors::ErrorDeclaration {
+        status_code: ::salvo::http::StatusCode::from_u16(#status)
+          .unwrap_or(::salvo::http::StatusCode::INTERNAL_SERVER_ERROR),
+        public: #public,
+        message: #message,
+        error_code: #code,
+      }
+    }
+  }
+}
+
+/// Derives `IntoErrorResponse`, `salvo::Writer` and `EndpointOutRegister` for a domain error.
+///
+/// Each variant (or the whole type) may declare `#[response(status = 404, public, message = "...", code = "...")]`:
+///
+/// - `status` - error status (400..=599), INTERNAL SERVER ERROR (500) by default;
+/// - `public` - send the error's own text to the client;
+/// - `message` - send this text instead (implies `public`);
+/// - `code` - application error code for the `x-error-code` header.
+///
+/// Type-level declaration is inherited by variants. Without `public` or `message`, the client gets the catalog text.
+///
+/// Non-generic types are registered at startup (see `cc_utils::errors::register_declared_error`), so `?`, `From` and
+/// `Consider` into `ErrorResponse` apply the declaration too; generic types need `consider_declared` or explicit
+/// registration.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+/// use salvo::Request;
+///
+/// #[derive(Debug, thiserror::Error, IntoErrorResponse)]
+/// enum UserError {
+///   #[error("user {0} not found")]
+///   #[response(status = 404, public, code = "user_not_found")]
+///   NotFound(i64),
+///   #[error("user {0} already exists")]
+///   #[response(status = 409, message = "User already exists.", code = "user_exists")]
+///   Exists(String),
+///   #[error(transparent)]
+///   Io(#[from] std::io::Error),
+/// }
+///
+/// fn find_user(id: i64) -> Result<String, UserError> {
+///   match id {
+///     1 => Ok("alice".into()),
+///     2 => Err(UserError::Exists("bob".into())),
+///     _ => Err(UserError::NotFound(id)),
+///   }
+/// }
+///
+/// #[endpoint]
+/// async fn get_user(req: &mut Request) -> MResult<Json<String>, UserError> {
+///   let id = req.param::<i64>("id").unwrap_or_default();
+///   Ok(Json(find_user(id)?, "get_user"))
+/// }
+///
+/// #[endpoint]
+/// async fn get_user_name(req: &mut Request) -> MResult<Json<String>> {
+///   let id = req.param::<i64>("id").consider_400_pub()?;
+///   json!(find_user(id)?)
+/// }
+///
+/// let error = ErrorResponse::from(UserError::NotFound(42));
+/// assert_eq!(error.status_code, Some(StatusCode::NOT_FOUND));
+/// assert_eq!(error.error_code.as_deref(), Some("user_not_found"));
+/// ```
+#[proc_macro_derive(IntoErrorResponse, attributes(response))]
+pub fn derive_into_error_response(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let type_declaration = Declaration::parse(&input.attrs, &Declaration::default())?;
+
+  let (declaration_body, declarations) = match &input.data {
+    Data::Enum(data) => {
+      let mut arms = vec![];
+      let mut declarations = vec![];
+      for variant in &data.variants {
+        let declaration = Declaration::parse(&variant.attrs, &type_declaration)?;
+        let ident = &variant.ident;
+        let pattern = match variant.fields {
+          Fields::Named(_) => quote! { Self::#ident { .. } },
+          Fields::Unnamed(_) => quote! { Self::#ident(..) },
+          Fields::Unit => quote! { Self::#ident },
+        };
+        let tokens = declaration.tokens();
+        arms.push(quote! { #pattern => #tokens, });
+        declarations.push(declaration);
+      }
+      if arms.is_empty() {
+        return Err(syn::Error::new_spanned(
+          name,
+          "`IntoErrorResponse` can't be derived for empty enums",
+        ));
+      }
+      (quote! { match self { #(#arms)* } }, declarations)
+    }
+    Data::Struct(_) => (type_declaration.tokens(), vec![type_declaration]),
+    Data::Union(_) => {
+      return Err(syn::Error::new_spanned(
+        name,
+        "`IntoErrorResponse` can't be derived for unions",
+      ));
+    }
+  };
+
+  let mut statuses: Vec<(u16, Vec<String>)> = vec![];
+  for declaration in &declarations {
+    let status = declaration.status();
+    let index = match statuses.iter().position(|(known, _)| *known == status) {
+      Some(index) => index,
+      None => {
+        statuses.push((status, vec![]));
+        statuses.len() - 1
+      }
+    };
+    if let Some(code) = &declaration.code
+      && !statuses[index].1.contains(code)
+    {
+      statuses[index].1.push(code.clone());
+    }
+  }
+  let statuses = statuses.iter().map(|(status, codes)| {
+    quote! {
+      (
+        ::salvo::http::StatusCode::from_u16(#status).unwrap_or(::salvo::http::StatusCode::INTERNAL_SERVER_ERROR),
+        vec![#(#codes),*],
+      )
+    }
+  });
+
+  // Generic types can't be registered for all their instantiations.
+  let registration = input.generics.params.is_empty().then(|| {
+    quote! {
+      const _: () = {
+        #[::cc_utils::__private::ctor::ctor(crate_path = ::cc_utils::__private::ctor)]
+        unsafe fn register() {
+          ::cc_utils::errors::register_declared_error::<#name>();
+        }
+      };
+    }
+  });
+
+  Ok(quote! {
+    #registration
+
+    impl #impl_generics ::cc_utils::errors::IntoErrorResponse for #name #ty_generics #where_clause {
+      fn declaration(&self) -> ::cc_utils::errors::ErrorDeclaration {
+        #declaration_body
+      }
+
+      fn declared_statuses() -> Vec<(::salvo::http::StatusCode, Vec<&'static str>)> {
+        vec![#(#statuses),*]
+      }
+    }
+
+    #[::salvo::async_trait]
+    impl #impl_generics ::salvo::Writer for #name #ty_generics #where_clause {
+      async fn write(self, req: &mut ::salvo::Request, depot: &mut ::salvo::Depot, res: &mut ::salvo::Response) {
+        let error = ::cc_utils::errors::IntoErrorResponse::into_error_response(self);
+        ::salvo::Writer::write(error, req, depot, res).await;
+      }
+    }
+
+    impl #impl_generics ::salvo::oapi::EndpointOutRegister for #name #ty_generics #where_clause {
+      fn register(components: &mut ::salvo::oapi::Components, operation: &mut ::salvo::oapi::Operation) {
+        <Self as ::cc_utils::errors::IntoErrorResponse>::register_declared(components, operation);
+      }
+    }
+  })
+}
@@ -1,13 +1,26 @@
-//! Macros for compressing data sent by the `salvo` server.
+//! Macros and builder for compressing data sent by the `salvo` server.
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use salvo::http::mime::{self, Mime};
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use salvo::prelude::{Compression, CompressionLevel};
 
 /// Brotli compression salvo's hoop.
 ///
 /// Usage:
 ///
 /// ```rust
-/// use salvo::Router;
+/// use salvo::prelude::*;
 /// use cc_utils::brotli;
 ///
+/// #[handler]
+/// async fn hello_compressed_json(res: &mut Response) {
+///   res.render(Json(serde_json::json!({ "hello": "world" })));
+/// }
+///
 /// let router = Router::with_hoop(brotli!()).path("new-compressed-json").get(hello_compressed_json);
 /// ```
 #[cfg(feature = "salvo")]
@@ -15,11 +28,145 @@
 #[macro_export]
 macro_rules! brotli {
   () => {
-    salvo::prelude::Compression::new()
-      .disable_all()
-      .enable_brotli(salvo::prelude::CompressionLevel::Minsize)
+    $crate::compression::CompressionBuilder::new()
+      .brotli(salvo::prelude::CompressionLevel::Minsize)
       .content_types(&[salvo::http::mime::APPLICATION_JSON])
-      .force_priority(true)
-      .min_length(10)
+      .build()
   };
 }
+
+/// Compression salvo's hoop with the given algorithms at default levels for JSON and MsgPack.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::compression;
+///
+/// let hoop = compression!(brotli, zstd, gzip);
+/// ```
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[macro_export]
+macro_rules! compression {
+  ($($algo:ident),+ $(,)?) => {
+    $crate::compression::CompressionBuilder::new()
+      $(.$algo(salvo::prelude::CompressionLevel::Default))+
+      .build()
+  };
+}
+
+/// Builder of the compression salvo's hoop.
+///
+/// Algorithms are prioritized in the order brotli, zstd, gzip, deflate.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// let hoop = CompressionBuilder::new()
+///   .brotli(CompressionLevel::Minsize)
+///   .gzip(CompressionLevel::Fastest)
+///   .min_length(1024)
+///   .build();
+/// ```
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[derive(Debug, Clone)]
+pub struct CompressionBuilder {
+  brotli: Option<CompressionLevel>,
+  zstd: Option<CompressionLevel>,
+  gzip: Option<CompressionLevel>,
+  deflate: Option<CompressionLevel>,
+  content_types: Vec<Mime>,
+  min_length: usize,
+  force_priority: bool,
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl Default for CompressionBuilder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl CompressionBuilder {
+  /// Creates a builder without enabled algorithms for JSON and MsgPack responses longer than 10 bytes.
+  pub fn new() -> Self {
+    Self {
+      brotli: None,
+      zstd: None,
+      gzip: None,
+      deflate: None,
+      content_types: vec![mime::APPLICATION_JSON, mime::APPLICATION_MSGPACK],
+      min_length: 10,
+      force_priority: true,
+    }
+  }
+
+  /// Enables brotli with the given level.
+  pub fn brotli(mut self, level: CompressionLevel) -> Self {
+    self.brotli = Some(level);
+    self
+  }
+
+  /// Enables zstd with the given level.
+  pub fn zstd(mut self, level: CompressionLevel) -> Self {
+    self.zstd = Some(level);
+    self
+  }
+
+  /// Enables gzip with the given level.
+  pub fn gzip(mut self, level: CompressionLevel) -> Self {
+    self.gzip = Some(level);
+    self
+  }
+
+  /// Enables deflate with the given level.
+  pub fn deflate(mut self, level: CompressionLevel) -> Self {
+    self.deflate = Some(level);
+    self
+  }
+
+  /// Replaces compressed content types.
+  pub fn content_types(mut self, content_types: &[Mime]) -> Self {
+    self.content_types = content_types.to_vec();
+    self
+  }
+
+  /// Sets minimal body length to compress.
+  pub fn min_length(mut self, min_length: usize) -> Self {
+    self.min_length = min_length;
+    self
+  }
+
+  /// Sets whether the server's algorithm priority overrides the client's one.
+  pub fn force_priority(mut self, force_priority: bool) -> Self {
+    self.force_priority = force_priority;
+    self
+  }
+
+  /// Builds the hoop.
+  pub fn build(&self) -> Compression {
+    let mut compression = Compression::new().disable_all();
+    if let Some(level) = self.brotli {
+      compression = compression.enable_brotli(level);
+    }
+    if let Some(level) = self.zstd {
+      compression = compression.enable_zstd(level);
+    }
+    if let Some(level) = self.gzip {
+      compression = compression.enable_gzip(level);
+    }
+    if let Some(level) = self.deflate {
+      compression = compression.enable_deflate(level);
+    }
+    compression
+      .content_types(&self.content_types)
+      .force_priority(self.force_priority)
+      .min_length(self.min_length)
+  }
+}
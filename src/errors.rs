@@ -4,9 +4,28 @@
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 use std::any::Any;
 
+use std::any::TypeId;
+
+use std::collections::BTreeMap;
+
+use std::sync::{Arc, RwLock};
+
+use std::panic::Location;
+
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+
+use serde::{Deserialize, Serialize};
+
+use http::StatusCode;
+
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-use salvo::http::StatusCode;
+use salvo::http::HeaderValue;
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use salvo::hyper::header::{ACCEPT, CONTENT_TYPE};
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
@@ -20,21 +39,183 @@ use salvo::{Depot, Request, Response};
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 use salvo::Writer as ServerResponseWriter;
 
+#[cfg(feature = "derive")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub use cc_utils_derive::IntoErrorResponse;
+
+use crate::messages::catalog_message;
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use crate::messages::request_locales;
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use crate::responses::negotiate;
+
 pub type BoxDynError = Box<dyn std::error::Error + 'static + Send + Sync>;
 
+/// Header that carries the application error code of an error response.
+pub const ERROR_CODE_HEADER: &str = "x-error-code";
+
+/// Stable machine-readable application error code which clients can branch on.
+///
+/// Implement it for your error code enums; strings are supported out of the box.
+pub trait ErrorCode {
+  fn error_code(&self) -> String;
+}
+
+impl ErrorCode for &str {
+  fn error_code(&self) -> String {
+    (*self).to_owned()
+  }
+}
+
+impl ErrorCode for String {
+  fn error_code(&self) -> String {
+    self.clone()
+  }
+}
+
+/// RFC 7807 problem details (`application/problem+json`) body, the envelope of every error body format.
+///
+/// `status`, `title` and `detail` are filled by `ErrorResponse` when it is written,
+/// so the public/private split of the error text is preserved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+  all(feature = "salvo", not(any(target_arch = "wasm32", target_arch = "wasm64"))),
+  derive(ToSchema)
+)]
+pub struct ProblemDetails {
+  #[serde(rename = "type")]
+  pub problem_type: String,
+  pub title: String,
+  pub status: u16,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub detail: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub instance: Option<String>,
+  #[serde(flatten)]
+  #[cfg_attr(
+    all(feature = "salvo", not(any(target_arch = "wasm32", target_arch = "wasm64"))),
+    salvo(schema(value_type = Object))
+  )]
+  pub extensions: BTreeMap<String, serde_json::Value>,
+}
+
+impl Default for ProblemDetails {
+  fn default() -> Self {
+    Self {
+      problem_type: "about:blank".into(),
+      title: String::new(),
+      status: 500,
+      detail: None,
+      instance: None,
+      extensions: BTreeMap::new(),
+    }
+  }
+}
+
 /// Data structure responsible for server errors.
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-#[derive(Debug)]
+///
+/// Serialization writes the `redacted` view, so private texts never leave the process by accident; use `unredacted`
+/// (or `serialize_unredacted` with `#[serde(serialize_with)]`) to keep them, e.g. for internal queues.
+/// The source, location and backtrace are never serialized.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct ErrorResponse {
-  #[cfg(feature = "salvo")]
-  #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+  #[serde(with = "status_code_serde")]
   pub status_code: Option<StatusCode>,
   pub error_text: String,
   pub original_text: Option<String>,
   pub public_error: bool,
+  /// Application error code, sent to the client in the `x-error-code` header.
+  pub error_code: Option<String>,
+  /// Problem details of the error; if set, `application/problem+json` is preferred unless the client asks otherwise.
+  pub problem: Option<Box<ProblemDetails>>,
+  /// The original error, which is logged with its whole `source()` chain and never sent to the client.
+  #[serde(skip)]
+  pub source: Option<Arc<dyn std::error::Error + Send + Sync>>,
+  /// Where the error was created through `From` or `Consider`; private logs only.
+  #[serde(skip)]
+  pub location: Option<&'static Location<'static>>,
+  /// Backtrace of the error creation (see `RUST_BACKTRACE`); private logs only.
+  #[cfg(feature = "backtrace")]
+  #[serde(skip)]
+  pub backtrace: Option<Arc<Backtrace>>,
+}
+
+/// Serializes `Option<StatusCode>` as an optional number.
+mod status_code_serde {
+  use http::StatusCode;
+  use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+  pub fn serialize<S: Serializer>(status_code: &Option<StatusCode>, serializer: S) -> Result<S::Ok, S::Error> {
+    status_code
+      .map(|status_code| status_code.as_u16())
+      .serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<StatusCode>, D::Error> {
+    Option::<u16>::deserialize(deserializer)?
+      .map(|status_code| StatusCode::from_u16(status_code).map_err(D::Error::custom))
+      .transpose()
+  }
+}
+
+/// Serialized fields of `ErrorResponse`.
+#[derive(Serialize)]
+struct ErrorResponseFields<'a> {
+  #[serde(with = "status_code_serde")]
+  status_code: Option<StatusCode>,
+  error_text: &'a str,
+  original_text: Option<&'a str>,
+  public_error: bool,
+  error_code: Option<&'a str>,
+  problem: Option<&'a ProblemDetails>,
+}
+
+impl<'a> From<&'a ErrorResponse> for ErrorResponseFields<'a> {
+  fn from(error: &'a ErrorResponse) -> Self {
+    Self {
+      status_code: error.status_code,
+      error_text: &error.error_text,
+      original_text: error.original_text.as_deref(),
+      public_error: error.public_error,
+      error_code: error.error_code.as_deref(),
+      problem: error.problem.as_deref(),
+    }
+  }
+}
+
+impl Serialize for ErrorResponse {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    ErrorResponseFields::from(&self.redacted()).serialize(serializer)
+  }
+}
+
+/// Serializes the error with its private texts; opt-in counterpart of the redacted `Serialize` of `ErrorResponse`.
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// let error = ErrorResponse::from("connection to db:5432 refused");
+/// assert!(!serde_json::to_string(&error).unwrap().contains("db:5432"));
+/// assert!(serde_json::to_string(&error.unredacted()).unwrap().contains("db:5432"));
+/// ```
+pub struct Unredacted<'a>(pub &'a ErrorResponse);
+
+impl Serialize for Unredacted<'_> {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_unredacted(self.0, serializer)
+  }
+}
+
+/// Serializes the error with its private texts, for `#[serde(serialize_with = "serialize_unredacted")]`.
+pub fn serialize_unredacted<S: serde::Serializer>(error: &ErrorResponse, serializer: S) -> Result<S::Ok, S::Error> {
+  ErrorResponseFields::from(error).serialize(serializer)
 }
 
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 impl std::fmt::Display for ErrorResponse {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.write_str(&format!(
@@ -46,197 +227,807 @@ impl std::fmt::Display for ErrorResponse {
   }
 }
 
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl std::error::Error for ErrorResponse {}
+/// `ErrorResponse` as `std::error::Error`, e.g. to return it from `main` or to keep it as the source of another error.
+///
+/// Converting it back into `ErrorResponse` with `?` or `From` restores the original response; inside `anyhow::Error`
+/// take it out with `downcast::<ResponseError>()` first.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// fn load() -> MResult<()> {
+///   Err(ErrorResponse::from("Config is missing.").with_404_pub().build())
+/// }
+///
+/// fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+///   load().map_err(ErrorResponse::into_std_error)?;
+///   Ok(())
+/// }
+///
+/// let error = ErrorResponse::from(run().unwrap_err());
+/// assert_eq!(error.status_code, Some(StatusCode::NOT_FOUND));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResponseError(pub ErrorResponse);
+
+impl std::fmt::Display for ResponseError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+impl std::error::Error for ResponseError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    self.0.source()
+  }
+}
+
+impl ErrorResponse {
+  /// The original error.
+  ///
+  /// `ErrorResponse` doesn't implement `std::error::Error` itself, so that any error converts into it with `?`; use
+  /// `into_std_error` where `std::error::Error` is required.
+  pub fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    self
+      .source
+      .as_ref()
+      .map(|source| source.as_ref() as &(dyn std::error::Error + 'static))
+  }
+
+  /// Wraps the error into `ResponseError`, which implements `std::error::Error`.
+  pub fn into_std_error(self) -> ResponseError {
+    ResponseError(self)
+  }
+
+  /// Records the place of the error creation, keeping the first one.
+  pub(crate) fn at(mut self, location: &'static Location<'static>) -> Self {
+    self.location.get_or_insert(location);
+    #[cfg(feature = "backtrace")]
+    if self.backtrace.is_none() {
+      self.backtrace = Some(Arc::new(Backtrace::capture()));
+    }
+    self
+  }
+
+  /// Iterates over the original error and its `source()` chain.
+  pub fn chain(&self) -> impl Iterator<Item = &(dyn std::error::Error + 'static)> {
+    std::iter::successors(self.source(), |error| error.source())
+  }
+
+  /// Copy which is safe to send to the user: only the public text, status, error code and problem details remain.
+  pub fn redacted(&self) -> Self {
+    Self {
+      status_code: self.status_code,
+      error_text: self.public_text(&[]),
+      public_error: true,
+      error_code: self.error_code.clone(),
+      problem: self.problem.clone(),
+      ..Default::default()
+    }
+  }
+
+  /// Opt-in serialization with the private texts (see `Unredacted`).
+  pub fn unredacted(&self) -> Unredacted<'_> {
+    Unredacted(self)
+  }
+
+  /// Text the user may see: the error text itself if public, the catalog message for the locales otherwise.
+  pub fn public_text(&self, locales: &[String]) -> String {
+    if self.public_error {
+      self.error_text.to_owned()
+    } else {
+      catalog_message(
+        locales,
+        self.status_code.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        self.error_code.as_deref(),
+      )
+    }
+  }
+
+  /// Public envelope of the error sent to the client: the public text goes to `detail`, the error code to `code`.
+  pub fn problem_details(&self, locales: &[String]) -> ProblemDetails {
+    let status_code = self.status_code.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut problem = self.problem.as_deref().cloned().unwrap_or_default();
+    problem.status = status_code.as_u16();
+    if problem.title.is_empty() {
+      problem.title = status_code.canonical_reason().unwrap_or("Error").to_owned();
+    }
+    problem.detail = Some(self.public_text(locales));
+    if let Some(error_code) = &self.error_code {
+      problem.extensions.insert("code".into(), error_code.as_str().into());
+    }
+    problem
+  }
+}
 
 /// Data structure responsible for client errors.
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CliError {
   pub message: String,
+  /// HTTP status code of the server response, if the error came from the server.
+  pub status_code: Option<u16>,
+  pub error_code: Option<String>,
 }
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
 impl std::fmt::Display for CliError {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.write_str(self.message.as_str())
   }
 }
 
+impl CliError {
+  /// Creates a client error from the parts of the server's error response.
+  ///
+  /// Plain text, JSON (`application/problem+json`) and MsgPack bodies are supported;
+  /// the `x-error-code` header takes precedence over the `code` member of the problem details.
+  pub fn from_body(status_code: u16, error_code: Option<&str>, content_type: &str, body: &[u8]) -> Self {
+    let mut error_code = error_code.map(str::to_owned);
+    let problem = if content_type.contains("json") {
+      serde_json::from_slice::<ProblemDetails>(body).ok()
+    } else if content_type.contains("msgpack") {
+      crate::msgpack::from_slice::<ProblemDetails>(body).ok()
+    } else {
+      None
+    };
+    let message = match problem {
+      Some(problem) => {
+        if error_code.is_none() {
+          error_code = problem
+            .extensions
+            .get("code")
+            .and_then(|code| code.as_str())
+            .map(str::to_owned);
+        }
+        problem.detail.unwrap_or(problem.title)
+      }
+      None => String::from_utf8_lossy(body).into_owned(),
+    };
+    Self {
+      message,
+      status_code: Some(status_code),
+      error_code,
+    }
+  }
+}
+
+#[cfg(feature = "reqwest")]
+impl CliError {
+  /// Creates a client error from the server's error response (see `from_body`).
+  pub async fn from_response(response: reqwest::Response) -> Self {
+    let status_code = response.status().as_u16();
+    let error_code = response
+      .headers()
+      .get(ERROR_CODE_HEADER)
+      .and_then(|value| value.to_str().ok())
+      .map(str::to_owned);
+    let content_type = response
+      .headers()
+      .get(reqwest::header::CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .unwrap_or_default()
+      .to_owned();
+    let body = response.bytes().await.unwrap_or_default();
+    Self::from_body(status_code, error_code.as_deref(), &content_type, &body)
+  }
+}
+
+/// Default (English) public description of the error status, sent to the client instead of private error texts.
+pub fn public_description(status_code: StatusCode) -> &'static str {
+  match status_code {
+    StatusCode::BAD_REQUEST => "Bad request.",
+    StatusCode::UNAUTHORIZED => "Unauthorized request.",
+    StatusCode::FORBIDDEN => "Access denied.",
+    StatusCode::NOT_FOUND => "Page or method not found.",
+    StatusCode::METHOD_NOT_ALLOWED => "Method not allowed.",
+    StatusCode::NOT_ACCEPTABLE => "Requested response format is not available.",
+    StatusCode::CONFLICT => "The request conflicts with the current state of the resource.",
+    StatusCode::GONE => "The resource is no longer available.",
+    StatusCode::PAYLOAD_TOO_LARGE => "Request body is too large.",
+    StatusCode::UNSUPPORTED_MEDIA_TYPE => "Request body format is not supported.",
+    StatusCode::UNPROCESSABLE_ENTITY => "Request data is invalid.",
+    StatusCode::LOCKED => "Your actions are locked.",
+    StatusCode::TOO_MANY_REQUESTS => "Too many requests. Try again later.",
+    StatusCode::INTERNAL_SERVER_ERROR => "Internal server error. Contact the administrator.",
+    StatusCode::BAD_GATEWAY => "Upstream service returned an invalid response.",
+    StatusCode::SERVICE_UNAVAILABLE => "Service is temporarily unavailable. Try again later.",
+    StatusCode::GATEWAY_TIMEOUT => "Upstream service did not respond in time.",
+    _ => "Specific error. Check with the administrator for details.",
+  }
+}
+
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[salvo::async_trait]
 impl ServerResponseWriter for ErrorResponse {
   /// Method for sending an error message to the client.
-  async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
-    res.status_code(self.status_code.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
+  async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let status_code = self.status_code.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    res.status_code(status_code);
+    let chain = self.chain().map(|cause| cause.to_string()).collect::<Vec<_>>();
+    let problem = self.problem_details(&request_locales(req, depot));
     if !self.public_error {
-      let public_error_desc = match self.status_code {
-        Some(StatusCode::BAD_REQUEST) => "Bad request.",
-        Some(StatusCode::UNAUTHORIZED) => "Unauthorized request.",
-        Some(StatusCode::FORBIDDEN) => "Access denied.",
-        Some(StatusCode::NOT_FOUND) => "Page or method not found.",
-        Some(StatusCode::METHOD_NOT_ALLOWED) => "Method not allowed.",
-        Some(StatusCode::LOCKED) => "Your actions is locked.",
-        Some(StatusCode::INTERNAL_SERVER_ERROR) => "Internal server error. Contact the administrator.",
-        _ => "Specific error. Check with the administrator for details.",
-      };
       tracing::error!(
         "Error with code {:?}: \"{}\", client will get: \"{}\"",
         self.status_code,
         self.error_text,
-        public_error_desc
+        problem.detail.as_deref().unwrap_or_default()
       );
-      if self.original_text.is_some() {
-        tracing::error!("The original error text: {:?}", self.original_text.unwrap());
-      }
-      res.render(public_error_desc);
     } else {
       tracing::error!("Error with code {:?}: \"{}\"", self.status_code, self.error_text);
-      if self.original_text.is_some() {
-        tracing::error!("The original error text: {:?}", self.original_text.unwrap());
+    }
+    if let Some(original_text) = &self.original_text {
+      tracing::error!("The original error text: {:?}", original_text);
+    }
+    if chain.len() > 1 {
+      tracing::error!("Error chain: {}", chain.join(": "));
+    }
+    if let Some(location) = self.location {
+      tracing::error!("Error created at {}", location);
+    }
+    #[cfg(feature = "backtrace")]
+    if let Some(backtrace) = &self.backtrace
+      && backtrace.status() == std::backtrace::BacktraceStatus::Captured
+    {
+      tracing::error!("Error backtrace:\n{}", backtrace);
+    }
+    if let Some(error_code) = &self.error_code {
+      match HeaderValue::from_str(error_code) {
+        Ok(value) => {
+          res.headers_mut().insert(ERROR_CODE_HEADER, value);
+        }
+        Err(e) => tracing::error!("Invalid error code {:?}: {:?}", error_code, e),
+      }
+    }
+    let format = error_body_format(req, self.problem.is_some());
+    let body = match format {
+      "application/problem+json" | "application/json" => serde_json::to_vec(&problem).map_err(|e| e.to_string()),
+      "application/msgpack" => crate::msgpack::to_vec(&problem).map_err(|e| e.to_string()),
+      "text/html" => {
+        res.render(salvo::writing::Text::Html(error_page(&problem)));
+        return;
+      }
+      _ => {
+        res.render(problem.detail.unwrap_or_default());
+        return;
+      }
+    };
+    match body {
+      Ok(body) => {
+        res.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(format));
+        res.write_body(body).ok();
+      }
+      Err(e) => {
+        tracing::error!("Failed to serialize problem details as {}: {}", format, e);
+        res.render(problem.detail.unwrap_or_default());
       }
-      res.render(&self.error_text);
     }
   }
 }
 
+/// Error body formats, all carrying the same `ProblemDetails` envelope (plain text carries only its `detail`).
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl EndpointOutRegister for ErrorResponse {
-  /// Registers error types for OpenAPI.
-  fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
-    operation.responses.insert(
-      "400",
-      salvo::oapi::Response::new("Bad request").add_content("text/plain", String::to_schema(components)),
-    );
-    operation.responses.insert(
-      "401",
-      salvo::oapi::Response::new("Unauthorized").add_content("text/plain", String::to_schema(components)),
-    );
-    operation.responses.insert(
-      "403",
-      salvo::oapi::Response::new("Forbidden").add_content("text/plain", String::to_schema(components)),
-    );
-    operation.responses.insert(
-      "404",
-      salvo::oapi::Response::new("Not found").add_content("text/plain", String::to_schema(components)),
-    );
-    operation.responses.insert(
-      "405",
-      salvo::oapi::Response::new("Method not allowed").add_content("text/plain", String::to_schema(components)),
-    );
-    operation.responses.insert(
-      "423",
-      salvo::oapi::Response::new("Locked").add_content("text/plain", String::to_schema(components)),
+const ERROR_BODY_FORMATS: [&str; 5] = [
+  "text/plain",
+  "application/problem+json",
+  "application/json",
+  "application/msgpack",
+  "text/html",
+];
+
+/// Picks the error body format by the `Accept` header.
+///
+/// Without a preference the format follows the request body (JSON or MsgPack), then `application/problem+json`
+/// for errors built with `as_problem`, then plain text.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+fn error_body_format(req: &Request, problem: bool) -> &'static str {
+  let content_type = req
+    .headers()
+    .get(CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .unwrap_or_default()
+    .to_ascii_lowercase();
+  let default = if content_type.contains("msgpack") {
+    "application/msgpack"
+  } else if content_type.contains("json") || problem {
+    "application/problem+json"
+  } else {
+    "text/plain"
+  };
+  let mut offered = vec![default];
+  offered.extend(ERROR_BODY_FORMATS.into_iter().filter(|format| *format != default));
+  let accept = req.headers().get(ACCEPT).and_then(|value| value.to_str().ok());
+  negotiate(accept, &offered).unwrap_or(default)
+}
+
+/// Renders a minimal HTML error page.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+fn error_page(problem: &ProblemDetails) -> String {
+  fn escape(text: &str) -> String {
+    text
+      .replace('&', "&amp;")
+      .replace('<', "&lt;")
+      .replace('>', "&gt;")
+      .replace('"', "&quot;")
+      .replace('\'', "&#39;")
+  }
+
+  let title = format!("{} {}", problem.status, escape(&problem.title));
+  let detail = escape(problem.detail.as_deref().unwrap_or_default());
+  let code = match problem.extensions.get("code").and_then(|code| code.as_str()) {
+    Some(code) => format!("<p><code>{}</code></p>", escape(code)),
+    None => String::new(),
+  };
+  format!(
+    "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title></head>\
+     <body><h1>{title}</h1><p>{detail}</p>{code}</body></html>"
+  )
+}
+
+/// Registers an error status with all error body formats.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub(crate) fn register_error_status(
+  components: &mut salvo::oapi::Components,
+  operation: &mut salvo::oapi::Operation,
+  status_code: &str,
+  description: &str,
+) {
+  operation.responses.insert(
+    status_code,
+    salvo::oapi::Response::new(description)
+      .add_content("text/plain", String::to_schema(components))
+      .add_content("application/problem+json", ProblemDetails::to_schema(components))
+      .add_content("application/msgpack", ProblemDetails::to_schema(components))
+      .add_content("text/html", String::to_schema(components)),
+  );
+}
+
+/// Registers an error status along with the application error codes it can carry in the `x-error-code` header.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub fn register_error_codes(
+  components: &mut salvo::oapi::Components,
+  operation: &mut salvo::oapi::Operation,
+  status_code: StatusCode,
+  error_codes: &[&str],
+) {
+  let mut response = salvo::oapi::Response::new(status_code.canonical_reason().unwrap_or("Error"))
+    .add_content("text/plain", String::to_schema(components))
+    .add_content("application/problem+json", ProblemDetails::to_schema(components))
+    .add_content("application/msgpack", ProblemDetails::to_schema(components))
+    .add_content("text/html", String::to_schema(components));
+  if !error_codes.is_empty() {
+    response = response.add_header(
+      ERROR_CODE_HEADER,
+      salvo::oapi::Header::new(
+        salvo::oapi::Object::with_type(salvo::oapi::BasicType::String).enum_values(error_codes.iter().copied()),
+      )
+      .description("Application error code."),
     );
-    operation.responses.insert(
-      "500",
-      salvo::oapi::Response::new("Internal server error").add_content("text/plain", String::to_schema(components)),
+  }
+  operation.responses.insert(status_code.as_str(), response);
+}
+
+/// Registers the given error statuses with all error body formats.
+///
+/// Use it in `EndpointOutRegister` implementations to document exactly the codes an endpoint can return.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub fn register_error_statuses(
+  components: &mut salvo::oapi::Components,
+  operation: &mut salvo::oapi::Operation,
+  status_codes: &[StatusCode],
+) {
+  for status_code in status_codes {
+    register_error_status(
+      components,
+      operation,
+      status_code.as_str(),
+      status_code.canonical_reason().unwrap_or("Error"),
     );
   }
 }
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl EndpointOutRegister for ErrorResponse {
+  /// Registers common error types for OpenAPI; use `Errors!` or `declare_errors!` to list the exact ones.
+  fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+    register_error_status(components, operation, "400", "Bad request");
+    register_error_status(components, operation, "401", "Unauthorized");
+    register_error_status(components, operation, "403", "Forbidden");
+    register_error_status(components, operation, "404", "Not found");
+    register_error_status(components, operation, "405", "Method not allowed");
+    register_error_status(components, operation, "423", "Locked");
+    register_error_status(components, operation, "500", "Internal server error");
+  }
+}
+
+/// `ErrorResponse` that documents only the listed error statuses in OpenAPI.
+///
+/// Up to 8 statuses can be listed, unused slots are zeros; use `declare_errors!` for more. Every listed status must be
+/// an error status (400..=599), otherwise the code using the type fails to compile. Prefer the `Errors!` macro to
+/// spell the type.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+/// use salvo::Request;
+/// use salvo::oapi::ToSchema;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize, ToSchema)]
+/// struct User {
+///   id: i64,
+///   name: String,
+/// }
+///
+/// fn find_user(id: i64) -> Option<User> {
+///   (id == 1).then(|| User { id, name: "Alice".into() })
+/// }
+///
+/// #[endpoint]
+/// async fn get_user(req: &mut Request) -> MResult<Json<User>, Errors![400, 404]> {
+///   let id = req.param::<i64>("id").consider_400_pub()?;
+///   let user = find_user(id).consider_404_pub()?;
+///   Ok(json!(user)?)
+/// }
+/// ```
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[derive(Debug)]
+pub struct Errors<
+  const A: u16,
+  const B: u16 = 0,
+  const C: u16 = 0,
+  const D: u16 = 0,
+  const E: u16 = 0,
+  const F: u16 = 0,
+  const G: u16 = 0,
+  const H: u16 = 0,
+>(pub ErrorResponse);
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<const A: u16, const B: u16, const C: u16, const D: u16, const E: u16, const F: u16, const G: u16, const H: u16>
+  Errors<A, B, C, D, E, F, G, H>
+{
+  /// Declared statuses, checked at compile time.
+  const STATUSES: [u16; 8] = {
+    let statuses = [A, B, C, D, E, F, G, H];
+    assert!(is_error_status(A), "`Errors` statuses must be within 400..=599");
+    let mut i = 1;
+    while i < statuses.len() {
+      assert!(
+        statuses[i] == 0 || is_error_status(statuses[i]),
+        "`Errors` statuses must be within 400..=599"
+      );
+      i += 1;
+    }
+    statuses
+  };
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<T, const A: u16, const B: u16, const C: u16, const D: u16, const E: u16, const F: u16, const G: u16, const H: u16>
+  From<T> for Errors<A, B, C, D, E, F, G, H>
+where
+  ErrorResponse: From<T>,
+{
+  #[track_caller]
+  fn from(value: T) -> Self {
+    let _ = Self::STATUSES;
+    Self(ErrorResponse::from(value))
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[salvo::async_trait]
+impl<const A: u16, const B: u16, const C: u16, const D: u16, const E: u16, const F: u16, const G: u16, const H: u16>
+  ServerResponseWriter for Errors<A, B, C, D, E, F, G, H>
+{
+  async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    self.0.write(req, depot, res).await;
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<const A: u16, const B: u16, const C: u16, const D: u16, const E: u16, const F: u16, const G: u16, const H: u16>
+  EndpointOutRegister for Errors<A, B, C, D, E, F, G, H>
+{
+  /// Registers only the declared error statuses for OpenAPI.
+  fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+    // Statuses are within 400..=599 here, so all of them are valid.
+    let status_codes = Self::STATUSES
+      .into_iter()
+      .filter(|code| *code != 0)
+      .filter_map(|code| StatusCode::from_u16(code).ok())
+      .collect::<Vec<_>>();
+    register_error_statuses(components, operation, &status_codes);
+  }
+}
+
+/// Checks whether the code is an error status (400..=599), for compile-time checks of declared statuses.
+#[doc(hidden)]
+pub const fn is_error_status(code: u16) -> bool {
+  matches!(code, 400..=599)
+}
+
+/// Spells the `Errors` type with up to 8 error statuses (400..=599).
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// async fn delete_user(id: i64) -> MResult<OK, Errors![404, 409]> {
+///   (id == 1).then_some(id).consider_404_pub()?;
+///   Ok(ok!()?)
+/// }
+/// ```
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[macro_export]
+macro_rules! Errors {
+  ($($status:literal),+ $(,)?) => {
+    cc_utils::errors::Errors<$($status),+>
+  };
+}
+
+/// Declares an error type that documents the listed error statuses and their application error codes in OpenAPI.
+///
+/// Any number of statuses can be listed; each must be an error status (400..=599), otherwise the declaration fails
+/// to compile.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// declare_errors!(pub UserErrors {
+///   404 => ["user_not_found"],
+///   409 => ["user_exists", "email_taken"],
+///   500,
+/// });
+///
+/// async fn create_user(name: &str) -> MResult<OK, UserErrors> {
+///   if name == "admin" {
+///     return Err(ErrorResponse::from("User exists.").with_409_pub().with_code("user_exists").build().into());
+///   }
+///   Ok(ok!()?)
+/// }
+/// ```
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[macro_export]
+macro_rules! declare_errors {
+  ($vis:vis $name:ident { $($status:literal $(=> [$($code:expr),* $(,)?])?),+ $(,)? }) => {
+    #[derive(Debug)]
+    $vis struct $name(pub cc_utils::errors::ErrorResponse);
+
+    $(
+      const _: () = assert!(
+        cc_utils::errors::is_error_status($status),
+        concat!("`", stringify!($status), "` is not an error status (400..=599)")
+      );
+    )+
+
+    impl<T> From<T> for $name
+    where
+      cc_utils::errors::ErrorResponse: From<T>,
+    {
+      #[track_caller]
+      fn from(value: T) -> Self {
+        Self(cc_utils::errors::ErrorResponse::from(value))
+      }
+    }
+
+    #[salvo::async_trait]
+    impl salvo::Writer for $name {
+      async fn write(self, req: &mut salvo::Request, depot: &mut salvo::Depot, res: &mut salvo::Response) {
+        salvo::Writer::write(self.0, req, depot, res).await;
+      }
+    }
+
+    impl salvo::oapi::EndpointOutRegister for $name {
+      fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+        $(
+          if let Ok(status_code) = salvo::http::StatusCode::from_u16($status) {
+            cc_utils::errors::register_error_codes(components, operation, status_code, &[$($($code),*)?]);
+          }
+        )+
+      }
+    }
+  };
+}
+
 #[allow(dead_code)]
 impl ErrorResponse {
+  /// Sets any error status; private errors are replaced with the catalog message for the client.
+  pub fn with_status(&mut self, status_code: StatusCode, public: bool) -> &mut Self {
+    self.status_code = Some(status_code);
+    self.public_error = public;
+    self
+  }
+
   /// Private error BAD REQUEST (400).
   pub fn with_400(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::BAD_REQUEST);
-    self.public_error = false;
-    self
+    self.with_status(StatusCode::BAD_REQUEST, false)
   }
 
   /// Public error BAD REQUEST (400).
   pub fn with_400_pub(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::BAD_REQUEST);
-    self.public_error = true;
-    self
+    self.with_status(StatusCode::BAD_REQUEST, true)
   }
 
   /// Private error UNAUTHORIZED (401).
   pub fn with_401(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::UNAUTHORIZED);
-    self.public_error = false;
-    self
+    self.with_status(StatusCode::UNAUTHORIZED, false)
   }
 
   /// Public error UNAUTHORIZED (401).
   pub fn with_401_pub(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::UNAUTHORIZED);
-    self.public_error = true;
-    self
+    self.with_status(StatusCode::UNAUTHORIZED, true)
   }
 
   /// Private error FORBIDDEN (403).
   pub fn with_403(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::FORBIDDEN);
-    self.public_error = false;
-    self
+    self.with_status(StatusCode::FORBIDDEN, false)
   }
 
   /// Public error FORBIDDEN (403).
   pub fn with_403_pub(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::FORBIDDEN);
-    self.public_error = true;
-    self
+    self.with_status(StatusCode::FORBIDDEN, true)
   }
 
   /// Private error NOT FOUND (404).
   pub fn with_404(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::NOT_FOUND);
-    self.public_error = false;
-    self
+    self.with_status(StatusCode::NOT_FOUND, false)
   }
 
   /// Public error NOT FOUND (404).
   pub fn with_404_pub(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::NOT_FOUND);
-    self.public_error = true;
-    self
+    self.with_status(StatusCode::NOT_FOUND, true)
   }
 
   /// Private error METHOD NOT ALLOWED (405).
   pub fn with_405(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::METHOD_NOT_ALLOWED);
-    self.public_error = false;
-    self
+    self.with_status(StatusCode::METHOD_NOT_ALLOWED, false)
   }
 
   /// Public error METHOD NOT ALLOWED (405).
   pub fn with_405_pub(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::METHOD_NOT_ALLOWED);
-    self.public_error = true;
-    self
+    self.with_status(StatusCode::METHOD_NOT_ALLOWED, true)
+  }
+
+  /// Private error NOT ACCEPTABLE (406).
+  pub fn with_406(&mut self) -> &mut Self {
+    self.with_status(StatusCode::NOT_ACCEPTABLE, false)
+  }
+
+  /// Public error NOT ACCEPTABLE (406).
+  pub fn with_406_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::NOT_ACCEPTABLE, true)
+  }
+
+  /// Private error CONFLICT (409).
+  pub fn with_409(&mut self) -> &mut Self {
+    self.with_status(StatusCode::CONFLICT, false)
+  }
+
+  /// Public error CONFLICT (409).
+  pub fn with_409_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::CONFLICT, true)
+  }
+
+  /// Private error GONE (410).
+  pub fn with_410(&mut self) -> &mut Self {
+    self.with_status(StatusCode::GONE, false)
+  }
+
+  /// Public error GONE (410).
+  pub fn with_410_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::GONE, true)
+  }
+
+  /// Private error PAYLOAD TOO LARGE (413).
+  pub fn with_413(&mut self) -> &mut Self {
+    self.with_status(StatusCode::PAYLOAD_TOO_LARGE, false)
+  }
+
+  /// Public error PAYLOAD TOO LARGE (413).
+  pub fn with_413_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::PAYLOAD_TOO_LARGE, true)
+  }
+
+  /// Private error UNSUPPORTED MEDIA TYPE (415).
+  pub fn with_415(&mut self) -> &mut Self {
+    self.with_status(StatusCode::UNSUPPORTED_MEDIA_TYPE, false)
+  }
+
+  /// Public error UNSUPPORTED MEDIA TYPE (415).
+  pub fn with_415_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::UNSUPPORTED_MEDIA_TYPE, true)
+  }
+
+  /// Private error UNPROCESSABLE ENTITY (422).
+  pub fn with_422(&mut self) -> &mut Self {
+    self.with_status(StatusCode::UNPROCESSABLE_ENTITY, false)
+  }
+
+  /// Public error UNPROCESSABLE ENTITY (422).
+  pub fn with_422_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::UNPROCESSABLE_ENTITY, true)
   }
 
   /// Private error LOCKED (423).
   pub fn with_423(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::LOCKED);
-    self.public_error = false;
-    self
+    self.with_status(StatusCode::LOCKED, false)
   }
 
   /// Public error LOCKED (423).
   pub fn with_423_pub(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::LOCKED);
-    self.public_error = true;
-    self
+    self.with_status(StatusCode::LOCKED, true)
+  }
+
+  /// Private error TOO MANY REQUESTS (429).
+  pub fn with_429(&mut self) -> &mut Self {
+    self.with_status(StatusCode::TOO_MANY_REQUESTS, false)
+  }
+
+  /// Public error TOO MANY REQUESTS (429).
+  pub fn with_429_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::TOO_MANY_REQUESTS, true)
   }
 
   /// Private error INTERNAL SERVER ERROR (500).
   pub fn with_500(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::INTERNAL_SERVER_ERROR);
-    self.public_error = false;
-    self
+    self.with_status(StatusCode::INTERNAL_SERVER_ERROR, false)
   }
 
   /// Public error INTERNAL SERVER ERROR (500).
   pub fn with_500_pub(&mut self) -> &mut Self {
-    self.status_code = Some(StatusCode::INTERNAL_SERVER_ERROR);
-    self.public_error = true;
-    self
+    self.with_status(StatusCode::INTERNAL_SERVER_ERROR, true)
+  }
+
+  /// Private error BAD GATEWAY (502).
+  pub fn with_502(&mut self) -> &mut Self {
+    self.with_status(StatusCode::BAD_GATEWAY, false)
+  }
+
+  /// Public error BAD GATEWAY (502).
+  pub fn with_502_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::BAD_GATEWAY, true)
+  }
+
+  /// Private error SERVICE UNAVAILABLE (503).
+  pub fn with_503(&mut self) -> &mut Self {
+    self.with_status(StatusCode::SERVICE_UNAVAILABLE, false)
+  }
+
+  /// Public error SERVICE UNAVAILABLE (503).
+  pub fn with_503_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::SERVICE_UNAVAILABLE, true)
+  }
+
+  /// Private error GATEWAY TIMEOUT (504).
+  pub fn with_504(&mut self) -> &mut Self {
+    self.with_status(StatusCode::GATEWAY_TIMEOUT, false)
+  }
+
+  /// Public error GATEWAY TIMEOUT (504).
+  pub fn with_504_pub(&mut self) -> &mut Self {
+    self.with_status(StatusCode::GATEWAY_TIMEOUT, true)
   }
 
   /// Changes error message text.
@@ -248,20 +1039,101 @@ impl ErrorResponse {
     self
   }
 
+  /// Sets application error code.
+  pub fn with_code(&mut self, error_code: impl ErrorCode) -> &mut Self {
+    self.error_code = Some(error_code.error_code());
+    self
+  }
+
+  /// Attaches the original error to log its `source()` chain.
+  pub fn with_source(&mut self, source: impl Into<BoxDynError>) -> &mut Self {
+    self.source = Some(Arc::from(source.into()));
+    self
+  }
+
+  /// Prefers RFC 7807 `application/problem+json` when the client has no preference.
+  pub fn as_problem(&mut self) -> &mut Self {
+    self.problem.get_or_insert_with(Box::default);
+    self
+  }
+
+  /// Sets problem type URI (implies `as_problem`).
+  pub fn with_problem_type(&mut self, problem_type: impl Into<String>) -> &mut Self {
+    self.problem.get_or_insert_with(Box::default).problem_type = problem_type.into();
+    self
+  }
+
+  /// Sets problem instance URI (implies `as_problem`).
+  pub fn with_instance(&mut self, instance: impl Into<String>) -> &mut Self {
+    self.problem.get_or_insert_with(Box::default).instance = Some(instance.into());
+    self
+  }
+
+  /// Adds public extension member to the problem details (implies `as_problem`).
+  pub fn with_extension(&mut self, key: impl Into<String>, value: impl Serialize) -> &mut Self {
+    match serde_json::to_value(value) {
+      Ok(value) => {
+        self
+          .problem
+          .get_or_insert_with(Box::default)
+          .extensions
+          .insert(key.into(), value);
+      }
+      Err(e) => tracing::error!("Failed to serialize problem extension: {:?}", e),
+    }
+    self
+  }
+
   /// Builds the response.
   pub fn build(&mut self) -> Self {
-    Self {
-      status_code: self.status_code,
-      error_text: self.error_text.to_owned(),
-      original_text: self.original_text.clone(),
-      public_error: self.public_error,
-    }
+    self.clone()
   }
 }
 
+/// Generates status shorthands of the `Consider` trait.
+macro_rules! impl_consider_shorthands {
+  ($($private:ident, $public:ident => $status:ident, $desc:literal;)+) => {
+    $(
+      #[doc = concat!("Shorthand for `consider` with private error ", $desc, ".")]
+      #[track_caller]
+      fn $private(self) -> Result<T, ErrorResponse>
+      where
+        Self: Sized,
+      {
+        self.consider(Some(StatusCode::$status), None::<String>, false)
+      }
+
+      #[doc = concat!("Shorthand for `consider` with public error ", $desc, ".")]
+      #[track_caller]
+      fn $public(self) -> Result<T, ErrorResponse>
+      where
+        Self: Sized,
+      {
+        self.consider(Some(StatusCode::$status), None::<String>, true)
+      }
+    )+
+  };
+}
+
+/// Generates status shorthands of the `ConsiderCli` trait.
+macro_rules! impl_consider_cli_shorthands {
+  ($($name:ident => $status:literal;)+) => {
+    $(
+      #[doc = concat!("Shorthand for `consider_cli` that marks the error with ", stringify!($status), " status.")]
+      fn $name(self) -> Result<T, CliError>
+      where
+        Self: Sized,
+      {
+        self.consider_cli(None).map_err(|mut e| {
+          e.status_code = Some($status);
+          e
+        })
+      }
+    )+
+  };
+}
+
 /// A trait that allows you to transform any error into an `ErrorResponse` by assigning additional parameters.
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 pub trait Consider<T> {
   fn consider(
     self,
@@ -269,403 +1141,913 @@ pub trait Consider<T> {
     error_text_replacement: Option<impl Into<String>>,
     public: bool,
   ) -> Result<T, ErrorResponse>;
-}
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-pub trait ConsiderCli<T> {
-  fn consider_cli(self, error_text_replacement: Option<String>) -> Result<T, CliError>;
-}
-
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl<T> Consider<T> for Result<T, ErrorResponse> {
-  /// Changes the parameters of a possible error to the specified ones.
-  fn consider(
+  /// Same as `consider`, but also attaches application error code.
+  #[track_caller]
+  fn consider_with_code(
     self,
     status_code: Option<StatusCode>,
     error_text_replacement: Option<impl Into<String>>,
     public: bool,
-  ) -> Result<T, ErrorResponse> {
-    self.map_err(|e| {
-      let mut new_error = ErrorResponse {
-        status_code,
-        error_text: e.error_text,
-        original_text: e.original_text,
-        public_error: public,
-      };
-      if error_text_replacement.is_some() {
-        new_error.original_text = Some(new_error.error_text.to_owned());
-        new_error.error_text = error_text_replacement.unwrap().into();
-      }
-      new_error
+    error_code: impl ErrorCode,
+  ) -> Result<T, ErrorResponse>
+  where
+    Self: Sized,
+  {
+    self
+      .consider(status_code, error_text_replacement, public)
+      .map_err(|mut e| {
+        e.error_code = Some(error_code.error_code());
+        e
+      })
+  }
+
+  /// Same as `consider`, but the replacement text is built only on error, with access to the original one.
+  ///
+  /// Usage:
+  ///
+  /// ```rust
+  /// use cc_utils::prelude::*;
+  ///
+  /// fn find_user(id: i64) -> Result<String, std::io::Error> {
+  ///   Err(std::io::Error::other(format!("no row with id {}", id)))
+  /// }
+  ///
+  /// fn user_name(id: i64) -> MResult<String> {
+  ///   find_user(id).consider_lazy(Some(StatusCode::NOT_FOUND), true, |e| {
+  ///     format!("User {} not found ({}).", id, e.error_text)
+  ///   })
+  /// }
+  ///
+  /// assert_eq!(user_name(7).unwrap_err().status_code, Some(StatusCode::NOT_FOUND));
+  /// ```
+  #[track_caller]
+  fn consider_lazy<S: Into<String>>(
+    self,
+    status_code: Option<StatusCode>,
+    public: bool,
+    error_text_replacement: impl FnOnce(&ErrorResponse) -> S,
+  ) -> Result<T, ErrorResponse>
+  where
+    Self: Sized,
+  {
+    self.consider(status_code, None::<String>, public).map_err(|mut e| {
+      let text = error_text_replacement(&e).into();
+      e.original_text = Some(std::mem::replace(&mut e.error_text, text));
+      e
     })
   }
+
+  impl_consider_shorthands!(
+    consider_400, consider_400_pub => BAD_REQUEST, "BAD REQUEST (400)";
+    consider_401, consider_401_pub => UNAUTHORIZED, "UNAUTHORIZED (401)";
+    consider_403, consider_403_pub => FORBIDDEN, "FORBIDDEN (403)";
+    consider_404, consider_404_pub => NOT_FOUND, "NOT FOUND (404)";
+    consider_405, consider_405_pub => METHOD_NOT_ALLOWED, "METHOD NOT ALLOWED (405)";
+    consider_406, consider_406_pub => NOT_ACCEPTABLE, "NOT ACCEPTABLE (406)";
+    consider_409, consider_409_pub => CONFLICT, "CONFLICT (409)";
+    consider_410, consider_410_pub => GONE, "GONE (410)";
+    consider_413, consider_413_pub => PAYLOAD_TOO_LARGE, "PAYLOAD TOO LARGE (413)";
+    consider_415, consider_415_pub => UNSUPPORTED_MEDIA_TYPE, "UNSUPPORTED MEDIA TYPE (415)";
+    consider_422, consider_422_pub => UNPROCESSABLE_ENTITY, "UNPROCESSABLE ENTITY (422)";
+    consider_423, consider_423_pub => LOCKED, "LOCKED (423)";
+    consider_429, consider_429_pub => TOO_MANY_REQUESTS, "TOO MANY REQUESTS (429)";
+    consider_500, consider_500_pub => INTERNAL_SERVER_ERROR, "INTERNAL SERVER ERROR (500)";
+    consider_502, consider_502_pub => BAD_GATEWAY, "BAD GATEWAY (502)";
+    consider_503, consider_503_pub => SERVICE_UNAVAILABLE, "SERVICE UNAVAILABLE (503)";
+    consider_504, consider_504_pub => GATEWAY_TIMEOUT, "GATEWAY TIMEOUT (504)";
+  );
 }
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl<T> ConsiderCli<T> for Result<T, CliError> {
-  /// Changes the parameters of a possible error to the specified ones.
-  fn consider_cli(self, error_text_replacement: Option<String>) -> Result<T, CliError> {
-    self.map_err(|e| {
-      let mut new_error = CliError { message: e.message };
-      if error_text_replacement.is_some() {
-        new_error.message = error_text_replacement.unwrap();
-      }
-      new_error
+pub trait ConsiderCli<T> {
+  fn consider_cli(self, error_text_replacement: Option<String>) -> Result<T, CliError>;
+
+  /// Same as `consider_cli`, but also attaches application error code.
+  fn consider_cli_with_code(
+    self,
+    error_text_replacement: Option<String>,
+    error_code: impl ErrorCode,
+  ) -> Result<T, CliError>
+  where
+    Self: Sized,
+  {
+    self.consider_cli(error_text_replacement).map_err(|mut e| {
+      e.error_code = Some(error_code.error_code());
+      e
+    })
+  }
+
+  /// Same as `consider_cli`, but the replacement text is built only on error, with access to the original one.
+  fn consider_cli_lazy<S: Into<String>>(
+    self,
+    error_text_replacement: impl FnOnce(&CliError) -> S,
+  ) -> Result<T, CliError>
+  where
+    Self: Sized,
+  {
+    self.consider_cli(None).map_err(|mut e| {
+      e.message = error_text_replacement(&e).into();
+      e
     })
   }
+
+  impl_consider_cli_shorthands!(
+    consider_cli_400 => 400;
+    consider_cli_401 => 401;
+    consider_cli_403 => 403;
+    consider_cli_404 => 404;
+    consider_cli_405 => 405;
+    consider_cli_406 => 406;
+    consider_cli_409 => 409;
+    consider_cli_410 => 410;
+    consider_cli_413 => 413;
+    consider_cli_415 => 415;
+    consider_cli_422 => 422;
+    consider_cli_423 => 423;
+    consider_cli_429 => 429;
+    consider_cli_500 => 500;
+    consider_cli_502 => 502;
+    consider_cli_503 => 503;
+    consider_cli_504 => 504;
+  );
 }
 
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl<T> Consider<T> for Result<T, String> {
+impl<T> Consider<T> for Result<T, ErrorResponse> {
   /// Changes the parameters of a possible error to the specified ones.
+  #[track_caller]
   fn consider(
     self,
     status_code: Option<StatusCode>,
     error_text_replacement: Option<impl Into<String>>,
     public: bool,
   ) -> Result<T, ErrorResponse> {
+    let location = Location::caller();
     self.map_err(|e| {
       let mut new_error = ErrorResponse {
         status_code,
-        error_text: e,
-        original_text: None,
         public_error: public,
+        ..e
       };
-      if error_text_replacement.is_some() {
+      if let Some(error_text_replacement) = error_text_replacement {
         new_error.original_text = Some(new_error.error_text.to_owned());
-        new_error.error_text = error_text_replacement.unwrap().into();
+        new_error.error_text = error_text_replacement.into();
       }
-      new_error
+      new_error.at(location)
     })
   }
 }
 
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl<T> Consider<T> for anyhow::Result<T> {
+impl<T> ConsiderCli<T> for Result<T, CliError> {
   /// Changes the parameters of a possible error to the specified ones.
+  fn consider_cli(self, error_text_replacement: Option<String>) -> Result<T, CliError> {
+    self.map_err(|e| {
+      let mut new_error = e;
+      if let Some(error_text_replacement) = error_text_replacement {
+        new_error.message = error_text_replacement;
+      }
+      new_error
+    })
+  }
+}
+
+impl<T> Consider<T> for Option<T> {
+  /// Turns a missing value into an error; the status defaults to NOT FOUND (404).
+  #[track_caller]
   fn consider(
     self,
     status_code: Option<StatusCode>,
     error_text_replacement: Option<impl Into<String>>,
     public: bool,
   ) -> Result<T, ErrorResponse> {
-    self.map_err(|e| {
+    let location = Location::caller();
+    self.ok_or_else(|| {
       let mut new_error = ErrorResponse {
-        status_code,
-        error_text: e.to_string(),
+        status_code: Some(status_code.unwrap_or(StatusCode::NOT_FOUND)),
+        error_text: "Value not found.".into(),
         original_text: None,
         public_error: public,
+        ..Default::default()
       };
-      if error_text_replacement.is_some() {
+      if let Some(error_text_replacement) = error_text_replacement {
         new_error.original_text = Some(new_error.error_text.to_owned());
-        new_error.error_text = error_text_replacement.unwrap().into();
+        new_error.error_text = error_text_replacement.into();
       }
-      new_error
+      new_error.at(location)
     })
   }
 }
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl<T> ConsiderCli<T> for Result<T, String> {
-  /// Changes the parameters of a possible error to the specified ones.
+impl<T> ConsiderCli<T> for Option<T> {
+  /// Turns a missing value into an error with NOT FOUND (404) status.
   fn consider_cli(self, error_text_replacement: Option<String>) -> Result<T, CliError> {
-    self.map_err(|e| {
-      let mut new_error = CliError { message: e };
-      if error_text_replacement.is_some() {
-        new_error.message = error_text_replacement.unwrap();
-      }
-      new_error
+    self.ok_or_else(|| CliError {
+      message: error_text_replacement.unwrap_or_else(|| "Value not found.".into()),
+      status_code: Some(404),
+      ..Default::default()
     })
   }
 }
 
+/// Status code for the well-known error types, used when no status is given explicitly.
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl<T> Consider<T> for Result<T, &str> {
+fn inferred_status(error: &(dyn std::error::Error + 'static)) -> Option<StatusCode> {
+  if let Some(e) = error.downcast_ref::<salvo::http::StatusError>() {
+    return Some(e.code);
+  }
+  if error.is::<salvo::http::ParseError>() {
+    return Some(StatusCode::BAD_REQUEST);
+  }
+  match error.downcast_ref::<salvo::Error>() {
+    Some(salvo::Error::HttpStatus(e)) => Some(e.code),
+    Some(salvo::Error::HttpParse(_)) => Some(StatusCode::BAD_REQUEST),
+    _ => None,
+  }
+}
+
+/// Without `salvo` there are no well-known error types.
+#[cfg(any(not(feature = "salvo"), target_arch = "wasm32", target_arch = "wasm64"))]
+fn inferred_status(_error: &(dyn std::error::Error + 'static)) -> Option<StatusCode> {
+  None
+}
+
+impl ErrorResponse {
+  /// Creates an error keeping the original one as the source.
+  ///
+  /// `ResponseError` is unwrapped back, registered `IntoErrorResponse` types get their declaration, other errors are
+  /// private.
+  fn from_boxed(error: BoxDynError) -> Self {
+    let error = match error.downcast::<ResponseError>() {
+      Ok(error) => return error.0,
+      Err(error) => error,
+    };
+    match declared(error.as_ref()) {
+      Some(declaration) => Self::from_declared(error, declaration),
+      None => Self::from_source(error),
+    }
+  }
+
+  /// Creates a private error keeping the original one as the source.
+  fn from_source(error: BoxDynError) -> Self {
+    Self {
+      status_code: inferred_status(error.as_ref()),
+      error_text: error.to_string(),
+      source: Some(Arc::from(error)),
+      ..Default::default()
+    }
+  }
+
+  /// Creates an error with the declared status, visibility, public message and error code.
+  fn from_declared(error: BoxDynError, declaration: ErrorDeclaration) -> Self {
+    let mut error = Self::from_source(error);
+    error.status_code = Some(declaration.status_code);
+    error.public_error = declaration.public || declaration.message.is_some();
+    error.error_code = declaration.error_code.map(str::to_owned);
+    if let Some(message) = declaration.message {
+      error.original_text = Some(std::mem::replace(&mut error.error_text, message.to_owned()));
+    }
+    error
+  }
+}
+
+impl<T, E: Into<BoxDynError>> Consider<T> for Result<T, E> {
   /// Changes the parameters of a possible error to the specified ones.
+  ///
+  /// Without the status code, it's inferred for the known error types (e.g. `salvo::http::StatusError`).
+  #[track_caller]
   fn consider(
     self,
     status_code: Option<StatusCode>,
     error_text_replacement: Option<impl Into<String>>,
     public: bool,
   ) -> Result<T, ErrorResponse> {
+    let location = Location::caller();
     self.map_err(|e| {
-      let mut new_error = ErrorResponse {
-        status_code,
-        error_text: e.to_owned(),
-        original_text: None,
-        public_error: public,
-      };
-      if error_text_replacement.is_some() {
+      let mut new_error = ErrorResponse::from_boxed(e.into());
+      new_error.status_code = status_code.or(new_error.status_code);
+      new_error.public_error = public;
+      if let Some(error_text_replacement) = error_text_replacement {
         new_error.original_text = Some(new_error.error_text.to_owned());
-        new_error.error_text = error_text_replacement.unwrap().into();
+        new_error.error_text = error_text_replacement.into();
       }
-      new_error
+      new_error.at(location)
     })
   }
 }
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl<T> ConsiderCli<T> for Result<T, &str> {
+impl<E: Into<BoxDynError>> From<E> for ErrorResponse {
+  /// Creates a private error from any error or string.
+  #[track_caller]
+  fn from(value: E) -> Self {
+    Self::from_boxed(value.into()).at(Location::caller())
+  }
+}
+
+impl<T, E: Into<BoxDynError>> ConsiderCli<T> for Result<T, E> {
   /// Changes the parameters of a possible error to the specified ones.
   fn consider_cli(self, error_text_replacement: Option<String>) -> Result<T, CliError> {
     self.map_err(|e| {
-      let mut new_error = CliError { message: e.to_owned() };
-      if error_text_replacement.is_some() {
-        new_error.message = error_text_replacement.unwrap();
+      let mut new_error = CliError::from(e);
+      if let Some(error_text_replacement) = error_text_replacement {
+        new_error.message = error_text_replacement;
       }
       new_error
     })
   }
 }
 
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl From<String> for ErrorResponse {
-  /// Creates a new error from a string.
-  fn from(value: String) -> Self {
-    Self {
-      #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-      status_code: None,
-      error_text: value,
-      original_text: None,
-      public_error: false,
+impl<E: Into<BoxDynError>> From<E> for CliError {
+  /// Creates a client error from any error or string; `ResponseError` is shown as `ErrorResponse`.
+  fn from(value: E) -> Self {
+    match value.into().downcast::<ResponseError>() {
+      Ok(error) => Self::from(error.0),
+      Err(error) => Self {
+        message: error.to_string(),
+        ..Default::default()
+      },
     }
   }
 }
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl From<String> for CliError {
-  /// Creates a new error from a string.
-  fn from(value: String) -> Self {
-    Self { message: value }
-  }
-}
-
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl From<&str> for ErrorResponse {
-  /// Creates a new error from a string.
-  fn from(value: &str) -> Self {
+impl From<ErrorResponse> for CliError {
+  /// Shows the error on the client side: private texts are replaced with the catalog message.
+  fn from(value: ErrorResponse) -> Self {
     Self {
-      #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-      status_code: None,
-      error_text: value.to_owned(),
-      original_text: None,
-      public_error: false,
+      message: value.public_text(&[]),
+      status_code: value.status_code.map(|status_code| status_code.as_u16()),
+      error_code: value.error_code,
     }
   }
 }
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl From<&str> for CliError {
-  /// Creates a new error from a string.
-  fn from(value: &str) -> Self {
+impl From<CliError> for ErrorResponse {
+  /// Forwards the client error (e.g. of an upstream service) as a private error with its status.
+  #[track_caller]
+  fn from(value: CliError) -> Self {
     Self {
-      message: value.to_owned(),
+      status_code: value
+        .status_code
+        .and_then(|status_code| StatusCode::from_u16(status_code).ok()),
+      error_text: value.message,
+      error_code: value.error_code,
+      ..Default::default()
     }
+    .at(Location::caller())
   }
 }
 
-/// Macro to simplify `Consider` trait implementation.
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-macro_rules! impl_consider {
-  ($e:ty) => {
-    #[cfg(feature = "salvo")]
-    #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-    impl<T> Consider<T> for Result<T, $e> {
-      /// Изменяет параметры возможной ошибки на указанные.
-      fn consider(
-        self,
-        status_code: Option<StatusCode>,
-        error_text_replacement: Option<impl Into<String>>,
-        public: bool,
-      ) -> Result<T, ErrorResponse> {
-        self.map_err(|e| {
-          let mut new_error = ErrorResponse {
-            status_code,
-            error_text: e.to_string(),
-            original_text: None,
-            public_error: public,
-          };
-          if error_text_replacement.is_some() {
-            new_error.original_text = Some(new_error.error_text.to_owned());
-            new_error.error_text = error_text_replacement.unwrap().into();
-          }
-          new_error
-        })
+impl<T> ConsiderCli<T> for Result<T, ErrorResponse> {
+  /// Changes the parameters of a possible error to the specified ones.
+  fn consider_cli(self, error_text_replacement: Option<String>) -> Result<T, CliError> {
+    self.map_err(|e| {
+      let mut new_error = CliError::from(e);
+      if let Some(error_text_replacement) = error_text_replacement {
+        new_error.message = error_text_replacement;
       }
-    }
+      new_error
+    })
+  }
+}
 
-    #[cfg(feature = "salvo")]
-    #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-    impl From<$e> for ErrorResponse {
-      /// Создаёт `ErrorResponse` из данной ошибки.
-      fn from(value: $e) -> Self {
-        value.to_string().into()
+impl<T> Consider<T> for Result<T, CliError> {
+  /// Changes the parameters of a possible error to the specified ones; the status is kept unless given.
+  #[track_caller]
+  fn consider(
+    self,
+    status_code: Option<StatusCode>,
+    error_text_replacement: Option<impl Into<String>>,
+    public: bool,
+  ) -> Result<T, ErrorResponse> {
+    let location = Location::caller();
+    self.map_err(|e| {
+      let mut new_error = ErrorResponse::from(e);
+      new_error.status_code = status_code.or(new_error.status_code);
+      new_error.public_error = public;
+      if let Some(error_text_replacement) = error_text_replacement {
+        new_error.original_text = Some(new_error.error_text.to_owned());
+        new_error.error_text = error_text_replacement.into();
       }
-    }
-  };
+      new_error.at(location)
+    })
+  }
 }
 
-/// Macro to simplify `ConsiderCli` trait implementation.
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-macro_rules! impl_consider_cli {
-  ($e:ty) => {
-    impl<T> ConsiderCli<T> for Result<T, $e> {
-      /// Изменяет параметры возможной ошибки на указанные.
-      fn consider_cli(self, error_text_replacement: Option<String>) -> Result<T, CliError> {
-        self.map_err(|e| {
-          let mut new_error = CliError { message: e.to_string() };
-          if error_text_replacement.is_some() {
-            new_error.message = error_text_replacement.unwrap();
-          }
-          new_error
-        })
-      }
-    }
+/// Status code, visibility, public message and application error code declared for an error value.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorDeclaration {
+  pub status_code: StatusCode,
+  /// Whether the error's own text is sent to the client.
+  pub public: bool,
+  /// Public text which replaces the error's own one.
+  pub message: Option<&'static str>,
+  pub error_code: Option<&'static str>,
+}
 
-    impl From<$e> for CliError {
-      /// Создаёт `CliError` из данной ошибки.
-      fn from(value: $e) -> Self {
-        value.to_string().into()
-      }
+/// Domain error which maps onto `ErrorResponse` with declared statuses; usually derived with
+/// `#[derive(IntoErrorResponse)]` (`derive` feature).
+///
+/// Derived types also implement `salvo::Writer` and `EndpointOutRegister`, so they can be returned from handlers
+/// as `MResult<T, MyError>` and document exactly their statuses and error codes. Registered types (see
+/// `register_declared_error`) keep their declaration through `?`, `From` and `Consider` as well.
+pub trait IntoErrorResponse: Into<BoxDynError> + Sized {
+  /// Declaration for this value.
+  fn declaration(&self) -> ErrorDeclaration;
+
+  /// All statuses with their error codes this type can produce.
+  fn declared_statuses() -> Vec<(StatusCode, Vec<&'static str>)>;
+
+  /// Converts the error keeping it as the source.
+  #[track_caller]
+  fn into_error_response(self) -> ErrorResponse {
+    let declaration = self.declaration();
+    ErrorResponse::from_declared(self.into(), declaration).at(Location::caller())
+  }
+
+  /// Registers the declared statuses for OpenAPI.
+  #[cfg(feature = "salvo")]
+  #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+  fn register_declared(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+    for (status_code, error_codes) in Self::declared_statuses() {
+      register_error_codes(components, operation, status_code, &error_codes);
     }
+  }
+}
+
+/// Finds the declaration of a registered `IntoErrorResponse` type.
+type DeclarationLookup = fn(&(dyn std::error::Error + 'static)) -> Option<ErrorDeclaration>;
+
+static DECLARED_ERRORS: RwLock<Vec<(TypeId, DeclarationLookup)>> = RwLock::new(Vec::new());
+
+/// Makes `?`, `From` and `Consider` apply the declaration of `E` like `consider_declared` does.
+///
+/// `#[derive(IntoErrorResponse)]` registers non-generic types at startup; call it for generic ones, e.g.
+/// `register_declared_error::<MyError<Db>>()`. Registering the same type twice does nothing.
+pub fn register_declared_error<E: IntoErrorResponse + std::error::Error + 'static>() {
+  fn lookup<E: IntoErrorResponse + std::error::Error + 'static>(
+    error: &(dyn std::error::Error + 'static),
+  ) -> Option<ErrorDeclaration> {
+    error.downcast_ref::<E>().map(E::declaration)
+  }
+
+  let Ok(mut lookups) = DECLARED_ERRORS.write() else {
+    return;
   };
+  if !lookups.iter().any(|(type_id, _)| *type_id == TypeId::of::<E>()) {
+    lookups.push((TypeId::of::<E>(), lookup::<E>));
+  }
 }
 
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(rmp_serde::encode::Error);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(rmp_serde::decode::Error);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(std::io::Error);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(std::string::FromUtf8Error);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(std::env::VarError);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(std::sync::mpsc::RecvError);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(serde_json::Error);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(BoxDynError);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(std::num::ParseIntError);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(std::num::ParseFloatError);
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(tracing::subscriber::SetGlobalDefaultError);
+/// Declaration of the error if its type is registered.
+fn declared(error: &(dyn std::error::Error + 'static)) -> Option<ErrorDeclaration> {
+  let lookups = DECLARED_ERRORS.read().ok()?;
+  lookups.iter().find_map(|(_, lookup)| lookup(error))
+}
 
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(salvo::Error);
+/// Converts domain errors into `ErrorResponse` with their declared statuses.
+///
+/// Needed for types which aren't registered with `register_declared_error` (derived generic types); for them the plain
+/// `Consider` or `?` would make private INTERNAL SERVER ERROR (500).
+pub trait ConsiderDeclared<T> {
+  fn consider_declared(self) -> Result<T, ErrorResponse>;
+}
 
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(salvo::hyper::http::status::InvalidStatusCode);
+impl<T, E: IntoErrorResponse> ConsiderDeclared<T> for Result<T, E> {
+  #[track_caller]
+  fn consider_declared(self) -> Result<T, ErrorResponse> {
+    let location = Location::caller();
+    self.map_err(|e| ErrorResponse {
+      location: Some(location),
+      ..e.into_error_response()
+    })
+  }
+}
 
+/// `consider` for depot lookup results (`Depot::obtain`, `Depot::get`), kept from 0.5 for compatibility.
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(salvo::http::ParseError);
+pub trait ConsiderDepot<T> {
+  #[deprecated(
+    since = "0.6.0",
+    note = "use `DepotExt` (`obtain_or_500`, `get_or_500`, `get_or_401`) instead"
+  )]
+  fn consider(
+    self,
+    status_code: Option<StatusCode>,
+    error_text_replacement: Option<impl Into<String>>,
+    public: bool,
+  ) -> Result<T, ErrorResponse>;
+}
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl<T> Consider<T> for Result<T, Option<&Box<dyn Any + Send + Sync>>> {
-  /// Изменяет параметры возможной ошибки на указанные.
+#[allow(deprecated)]
+impl<T> ConsiderDepot<T> for Result<T, Option<&Box<dyn Any + Send + Sync>>> {
+  #[track_caller]
   fn consider(
     self,
     status_code: Option<StatusCode>,
     error_text_replacement: Option<impl Into<String>>,
     public: bool,
   ) -> Result<T, ErrorResponse> {
+    let location = Location::caller();
     self.map_err(|_| {
       let mut new_error = ErrorResponse {
         status_code,
         error_text: "Depot obtain failed!".into(),
-        original_text: None,
         public_error: public,
+        ..Default::default()
       };
-      if error_text_replacement.is_some() {
-        new_error.original_text = Some(new_error.error_text.to_owned());
-        new_error.error_text = error_text_replacement.unwrap().into();
+      if let Some(error_text_replacement) = error_text_replacement {
+        new_error.original_text = Some(std::mem::replace(
+          &mut new_error.error_text,
+          error_text_replacement.into(),
+        ));
       }
-      new_error
+      new_error.at(location)
     })
   }
 }
 
+/// Depot access that fails with `ErrorResponse` instead of `Option<&Box<dyn Any>>`.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+/// use salvo::Depot;
+///
+/// struct DbPool;
+///
+/// struct User {
+///   name: String,
+/// }
+///
+/// fn greet(depot: &Depot) -> MResult<String> {
+///   let _pool = depot.obtain_or_500::<DbPool>()?;
+///   let user = depot.get_or_401::<User>("user")?;
+///   Ok(format!("Hello, {}!", user.name))
+/// }
+/// ```
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl From<Option<&Box<dyn Any + Send + Sync>>> for ErrorResponse {
-  /// Создаёт `ErrorResponse` из данной ошибки.
-  fn from(_value: Option<&Box<(dyn Any + Send + Sync + 'static)>>) -> Self {
-    "Depot obtain failed!".into()
-  }
+pub trait DepotExt {
+  /// Obtains the injected value of type `T` or fails with private INTERNAL SERVER ERROR (500).
+  fn obtain_or_500<T: Any + Send + Sync>(&self) -> Result<&T, ErrorResponse>;
+
+  /// Gets the value by key or fails with private INTERNAL SERVER ERROR (500).
+  fn get_or_500<V: Any + Send + Sync>(&self, key: &str) -> Result<&V, ErrorResponse>;
+
+  /// Gets the value by key or fails with public UNAUTHORIZED (401), e.g. for the authorized user.
+  fn get_or_401<V: Any + Send + Sync>(&self, key: &str) -> Result<&V, ErrorResponse>;
 }
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl<T, U> Consider<T> for Result<T, std::sync::mpsc::SendError<U>> {
-  /// Изменяет параметры возможной ошибки на указанные.
-  fn consider(
-    self,
-    status_code: Option<StatusCode>,
-    error_text_replacement: Option<impl Into<String>>,
-    public: bool,
-  ) -> Result<T, ErrorResponse> {
-    self.map_err(|e| {
-      let mut new_error = ErrorResponse {
-        status_code,
-        error_text: e.to_string(),
-        original_text: None,
-        public_error: public,
-      };
-      if error_text_replacement.is_some() {
-        new_error.original_text = Some(new_error.error_text.to_owned());
-        new_error.error_text = error_text_replacement.unwrap().into();
-      }
-      new_error
-    })
+impl DepotExt for Depot {
+  #[track_caller]
+  fn obtain_or_500<T: Any + Send + Sync>(&self) -> Result<&T, ErrorResponse> {
+    self
+      .obtain::<T>()
+      .ok()
+      .consider_lazy(Some(StatusCode::INTERNAL_SERVER_ERROR), false, |_| {
+        format!("Depot obtain failed for `{}`!", std::any::type_name::<T>())
+      })
   }
-}
 
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl<U> From<std::sync::mpsc::SendError<U>> for ErrorResponse {
-  /// Создаёт `ErrorResponse` из данной ошибки.
-  fn from(value: std::sync::mpsc::SendError<U>) -> Self {
-    value.to_string().into()
+  #[track_caller]
+  fn get_or_500<V: Any + Send + Sync>(&self, key: &str) -> Result<&V, ErrorResponse> {
+    self
+      .get::<V>(key)
+      .ok()
+      .consider_lazy(Some(StatusCode::INTERNAL_SERVER_ERROR), false, |_| {
+        format!("Depot get failed for `{}`!", key)
+      })
+  }
+
+  #[track_caller]
+  fn get_or_401<V: Any + Send + Sync>(&self, key: &str) -> Result<&V, ErrorResponse> {
+    self.get::<V>(key).ok().consider_401_pub()
   }
 }
 
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(salvo::http::header::ToStrError);
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::msgpack::{MsgPackEncoding, to_vec_with};
 
-#[cfg(feature = "reqwest")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(reqwest::Error);
+  fn not_found() -> ErrorResponse {
+    ErrorResponse::from("User 42 not found.")
+      .with_404_pub()
+      .with_code("user_not_found")
+      .build()
+  }
 
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-impl_consider!(salvo::http::errors::StatusError);
-
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl_consider_cli!(rmp_serde::encode::Error);
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl_consider_cli!(rmp_serde::decode::Error);
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl_consider_cli!(std::io::Error);
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl_consider_cli!(std::string::FromUtf8Error);
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl_consider_cli!(serde_json::Error);
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl_consider_cli!(BoxDynError);
+  fn assert_same(cli_error: CliError, expected: CliError) {
+    assert_eq!(cli_error.message, expected.message);
+    assert_eq!(cli_error.status_code, expected.status_code);
+    assert_eq!(cli_error.error_code, expected.error_code);
+  }
 
-#[cfg(feature = "reqwest")]
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-impl_consider_cli!(reqwest::Error);
+  #[test]
+  fn cli_error_from_json_problem() {
+    let error = not_found();
+    let body = serde_json::to_vec(&error.problem_details(&[])).unwrap();
+    let cli_error = CliError::from_body(404, None, "application/problem+json", &body);
+    assert_eq!(cli_error.message, "User 42 not found.");
+    assert_eq!(cli_error.error_code.as_deref(), Some("user_not_found"));
+    assert_same(cli_error, CliError::from(error));
+  }
+
+  #[test]
+  fn cli_error_from_msgpack_problem() {
+    let error = not_found();
+    for encoding in [MsgPackEncoding::Positional, MsgPackEncoding::Named] {
+      let body = to_vec_with(&error.problem_details(&[]), encoding).unwrap();
+      let cli_error = CliError::from_body(404, None, "application/msgpack", &body);
+      assert_same(cli_error, CliError::from(error.clone()));
+    }
+  }
+
+  #[test]
+  fn cli_error_from_plain_text() {
+    let cli_error = CliError::from_body(
+      404,
+      Some("user_not_found"),
+      "text/plain; charset=utf-8",
+      b"User 42 not found.",
+    );
+    assert_same(cli_error, CliError::from(not_found()));
+  }
+
+  #[test]
+  fn cli_error_prefers_header_error_code() {
+    let body = serde_json::to_vec(&not_found().problem_details(&[])).unwrap();
+    let cli_error = CliError::from_body(404, Some("header_code"), "application/problem+json", &body);
+    assert_eq!(cli_error.error_code.as_deref(), Some("header_code"));
+  }
+
+  #[test]
+  fn cli_error_from_private_error_gets_public_text() {
+    let error = ErrorResponse::from("connection to db:5432 refused").with_500().build();
+    let body = serde_json::to_vec(&error.problem_details(&[])).unwrap();
+    let cli_error = CliError::from_body(500, None, "application/problem+json", &body);
+    assert_eq!(cli_error.message, public_description(StatusCode::INTERNAL_SERVER_ERROR));
+    assert_same(cli_error, CliError::from(error));
+  }
+
+  #[test]
+  fn cli_error_from_undecodable_body_keeps_text() {
+    let cli_error = CliError::from_body(502, None, "application/json", b"<html>Bad gateway</html>");
+    assert_eq!(cli_error.message, "<html>Bad gateway</html>");
+    assert_eq!(cli_error.status_code, Some(502));
+  }
+
+  #[cfg(feature = "salvo")]
+  #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+  fn body_format(accept: Option<&'static str>, content_type: Option<&'static str>, problem: bool) -> &'static str {
+    let mut req = Request::new();
+    if let Some(accept) = accept {
+      req.headers_mut().insert(ACCEPT, HeaderValue::from_static(accept));
+    }
+    if let Some(content_type) = content_type {
+      req
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    }
+    error_body_format(&req, problem)
+  }
+
+  #[cfg(feature = "salvo")]
+  #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+  #[test]
+  fn error_body_format_without_preferences() {
+    assert_eq!(body_format(None, None, false), "text/plain");
+    assert_eq!(body_format(None, None, true), "application/problem+json");
+    assert_eq!(body_format(Some("*/*"), None, false), "text/plain");
+  }
+
+  #[cfg(feature = "salvo")]
+  #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+  #[test]
+  fn error_body_format_follows_request_content_type() {
+    assert_eq!(
+      body_format(None, Some("application/json"), false),
+      "application/problem+json"
+    );
+    assert_eq!(
+      body_format(None, Some("Application/JSON; charset=utf-8"), false),
+      "application/problem+json"
+    );
+    assert_eq!(
+      body_format(Some("*/*"), Some("application/json"), false),
+      "application/problem+json"
+    );
+    assert_eq!(
+      body_format(None, Some("application/msgpack"), true),
+      "application/msgpack"
+    );
+    assert_eq!(body_format(None, Some("text/plain"), false), "text/plain");
+  }
+
+  #[cfg(feature = "salvo")]
+  #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+  #[test]
+  fn error_body_format_prefers_accept_over_content_type() {
+    assert_eq!(
+      body_format(Some("text/plain"), Some("application/json"), true),
+      "text/plain"
+    );
+    assert_eq!(
+      body_format(Some("application/json"), Some("application/msgpack"), false),
+      "application/json"
+    );
+    assert_eq!(
+      body_format(Some("application/problem+json"), None, false),
+      "application/problem+json"
+    );
+    assert_eq!(
+      body_format(Some("application/*"), None, false),
+      "application/problem+json"
+    );
+    assert_eq!(
+      body_format(Some("text/html, */*;q=0.1"), Some("application/json"), false),
+      "text/html"
+    );
+  }
+
+  #[cfg(feature = "salvo")]
+  #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+  #[test]
+  fn error_body_format_falls_back_on_unacceptable_accept() {
+    assert_eq!(body_format(Some("image/png"), None, false), "text/plain");
+    assert_eq!(
+      body_format(Some("image/png"), Some("application/json"), false),
+      "application/problem+json"
+    );
+  }
+
+  #[cfg(feature = "salvo")]
+  #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+  #[test]
+  fn registered_error_has_problem_details_schema() {
+    let mut components = salvo::oapi::Components::new();
+    let mut operation = salvo::oapi::Operation::new();
+    register_error_codes(
+      &mut components,
+      &mut operation,
+      StatusCode::NOT_FOUND,
+      &["user_not_found"],
+    );
+    let operation = serde_json::to_value(&operation).unwrap();
+    let components = serde_json::to_value(&components).unwrap();
+
+    let response = &operation["responses"]["404"];
+    assert_eq!(
+      response["headers"][ERROR_CODE_HEADER]["schema"]["enum"][0],
+      "user_not_found"
+    );
+    let content = &response["content"];
+    assert!(content.get("text/plain").is_some());
+    assert_eq!(content["application/msgpack"], content["application/problem+json"]);
+    let schema = &content["application/problem+json"]["schema"];
+    let schema = match schema["$ref"].as_str() {
+      Some(reference) => &components["schemas"][reference.trim_start_matches("#/components/schemas/")],
+      None => schema,
+    };
+    let schema = schema.to_string();
+    for property in ["type", "title", "status", "detail", "instance"] {
+      assert!(
+        schema.contains(&format!("\"{property}\"")),
+        "no `{property}` in {schema}"
+      );
+    }
+  }
+
+  #[test]
+  fn serialized_private_error_has_no_private_text() {
+    let error = ErrorResponse::from("connection to db:5432 refused")
+      .with_code("db_down")
+      .build();
+    let json = serde_json::to_string(&error).unwrap();
+    assert!(!json.contains("db:5432"));
+    assert!(json.contains("db_down"));
+    for encoding in [MsgPackEncoding::Positional, MsgPackEncoding::Named] {
+      let body = to_vec_with(&error, encoding).unwrap();
+      assert!(!body.windows(b"db:5432".len()).any(|window| window == b"db:5432"));
+    }
+    let restored: ErrorResponse = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.error_text, error.public_text(&[]));
+    assert_eq!(restored.original_text, None);
+  }
+
+  #[test]
+  fn serialized_public_error_keeps_text() {
+    let json = serde_json::to_string(&not_found()).unwrap();
+    let restored: ErrorResponse = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.status_code, Some(StatusCode::NOT_FOUND));
+    assert_eq!(restored.error_text, "User 42 not found.");
+    assert_eq!(restored.error_code.as_deref(), Some("user_not_found"));
+  }
+
+  #[test]
+  fn unredacted_serialization_keeps_private_text() {
+    let error = ErrorResponse::from("connection to db:5432 refused").with_500().build();
+    let json = serde_json::to_string(&error.unredacted()).unwrap();
+    let restored: ErrorResponse = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.error_text, error.error_text);
+    assert_eq!(restored.original_text, error.original_text);
+    assert!(!restored.public_error);
+  }
+
+  #[test]
+  fn std_error_round_trip_keeps_response() {
+    let source = std::io::Error::other("disk is full");
+    let error = ErrorResponse::from(source).with_500().build();
+    let boxed: BoxDynError = error.clone().into_std_error().into();
+    let restored = ErrorResponse::from(boxed);
+    assert_eq!(restored.status_code, error.status_code);
+    assert_eq!(restored.error_text, "disk is full");
+    assert_eq!(restored.location, error.location);
+  }
+
+  #[test]
+  fn std_error_exposes_source_chain() {
+    let error = ErrorResponse::from(std::io::Error::other("disk is full")).into_std_error();
+    let source = std::error::Error::source(&error).unwrap();
+    assert_eq!(source.to_string(), "disk is full");
+    let error = anyhow::Error::from(not_found().into_std_error());
+    assert!(error.chain().any(|e| e.is::<ResponseError>()));
+  }
+
+  #[test]
+  fn cli_error_from_std_error_is_public_view() {
+    assert_same(
+      CliError::from(not_found().into_std_error()),
+      CliError::from(not_found()),
+    );
+  }
+
+  /// Declared error for the registry tests; `N` makes distinct types, since registrations are global.
+  #[derive(Debug)]
+  struct Declared<const N: u8>(Option<&'static str>);
+
+  impl<const N: u8> std::fmt::Display for Declared<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      write!(f, "declared error {}", N)
+    }
+  }
+
+  impl<const N: u8> std::error::Error for Declared<N> {}
+
+  impl<const N: u8> IntoErrorResponse for Declared<N> {
+    fn declaration(&self) -> ErrorDeclaration {
+      ErrorDeclaration {
+        status_code: StatusCode::CONFLICT,
+        public: true,
+        message: self.0,
+        error_code: Some("declared"),
+      }
+    }
+
+    fn declared_statuses() -> Vec<(StatusCode, Vec<&'static str>)> {
+      vec![(StatusCode::CONFLICT, vec!["declared"])]
+    }
+  }
+
+  #[test]
+  fn registered_error_keeps_declaration_through_question_mark() {
+    register_declared_error::<Declared<1>>();
+    register_declared_error::<Declared<1>>();
+    fn fail() -> Result<(), ErrorResponse> {
+      Err(Declared::<1>(None))?;
+      Ok(())
+    }
+    let error = fail().unwrap_err();
+    assert_eq!(error.status_code, Some(StatusCode::CONFLICT));
+    assert!(error.public_error);
+    assert_eq!(error.error_code.as_deref(), Some("declared"));
+    assert_eq!(error.public_text(&[]), "declared error 1");
+    assert!(error.location.is_some());
+  }
+
+  #[test]
+  fn registered_error_message_replaces_text() {
+    register_declared_error::<Declared<2>>();
+    let error = ErrorResponse::from(Declared::<2>(Some("Already exists.")));
+    assert_eq!(error.public_text(&[]), "Already exists.");
+    assert_eq!(error.original_text.as_deref(), Some("declared error 2"));
+    let error = Err::<(), _>(Declared::<2>(None)).consider_404().unwrap_err();
+    assert_eq!(error.status_code, Some(StatusCode::NOT_FOUND));
+    assert!(!error.public_error);
+  }
+
+  #[test]
+  fn unregistered_error_is_private() {
+    let error = ErrorResponse::from(Declared::<3>(None));
+    assert_eq!(error.status_code, None);
+    assert!(!error.public_error);
+    assert_eq!(error.error_code, None);
+    let error = Err::<(), _>(Declared::<3>(None)).consider_declared().unwrap_err();
+    assert_eq!(error.status_code, Some(StatusCode::CONFLICT));
+  }
+}
@@ -2,9 +2,19 @@
 
 pub mod compression;
 pub mod errors;
+pub mod messages;
+pub mod msgpack;
 pub mod requests;
 pub mod responses;
 pub mod results;
 pub mod tracing;
 
 pub mod prelude;
+
+/// Re-exports used by the code generated with `#[derive(IntoErrorResponse)]`.
+#[cfg(feature = "derive")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[doc(hidden)]
+pub mod __private {
+  pub use ctor;
+}
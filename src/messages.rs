@@ -0,0 +1,216 @@
+//! Localizable public error messages for `ErrorResponse`.
+
+use std::collections::HashMap;
+
+use std::sync::{Arc, RwLock};
+
+use http::StatusCode;
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use salvo::hyper::header::ACCEPT_LANGUAGE;
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use salvo::{Depot, Request};
+
+use crate::errors::public_description;
+
+/// Depot key of the request locale (`String`), which takes precedence over `Accept-Language`.
+pub const LOCALE_DEPOT_KEY: &str = "cc_utils::locale";
+
+/// Source of public texts for private errors.
+pub trait MessageCatalog: Send + Sync {
+  /// Returns the text for the locale, status and application error code, or `None` to try the next locale.
+  fn message(&self, locale: &str, status_code: StatusCode, error_code: Option<&str>) -> Option<String>;
+}
+
+/// Default catalog with English texts for any locale.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnglishCatalog;
+
+impl MessageCatalog for EnglishCatalog {
+  fn message(&self, _locale: &str, status_code: StatusCode, _error_code: Option<&str>) -> Option<String> {
+    Some(public_description(status_code).to_owned())
+  }
+}
+
+/// In-memory catalog; error code texts take precedence over status texts.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// set_message_catalog(
+///   StaticCatalog::new()
+///     .with_status("ru", StatusCode::NOT_FOUND, "Страница или метод не найдены.")
+///     .with_code("ru", "user_not_found", "Пользователь не найден."),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StaticCatalog {
+  statuses: HashMap<(String, u16), String>,
+  codes: HashMap<(String, String), String>,
+}
+
+impl StaticCatalog {
+  /// Creates an empty catalog.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds the text for the status in the locale.
+  pub fn with_status(mut self, locale: impl Into<String>, status_code: StatusCode, text: impl Into<String>) -> Self {
+    self
+      .statuses
+      .insert((locale.into().to_lowercase(), status_code.as_u16()), text.into());
+    self
+  }
+
+  /// Adds the text for the application error code in the locale.
+  pub fn with_code(
+    mut self,
+    locale: impl Into<String>,
+    error_code: impl Into<String>,
+    text: impl Into<String>,
+  ) -> Self {
+    self
+      .codes
+      .insert((locale.into().to_lowercase(), error_code.into()), text.into());
+    self
+  }
+}
+
+impl MessageCatalog for StaticCatalog {
+  fn message(&self, locale: &str, status_code: StatusCode, error_code: Option<&str>) -> Option<String> {
+    let locale = locale.to_lowercase();
+    error_code
+      .and_then(|error_code| self.codes.get(&(locale.clone(), error_code.to_owned())))
+      .or_else(|| self.statuses.get(&(locale, status_code.as_u16())))
+      .cloned()
+  }
+}
+
+static CATALOG: RwLock<Option<Arc<dyn MessageCatalog>>> = RwLock::new(None);
+
+/// Replaces the global message catalog; locales it doesn't know fall back to `EnglishCatalog`.
+pub fn set_message_catalog(catalog: impl MessageCatalog + 'static) {
+  match CATALOG.write() {
+    Ok(mut guard) => *guard = Some(Arc::new(catalog)),
+    Err(e) => tracing::error!("Failed to set message catalog: {:?}", e),
+  }
+}
+
+/// Preferred locales of the request: the depot-stored one first, then `Accept-Language` by quality.
+///
+/// Regional tags are followed by their primary language (`ru-RU`, then `ru`).
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub fn request_locales(req: &Request, depot: &Depot) -> Vec<String> {
+  let mut tags = vec![];
+  if let Ok(locale) = depot.get::<String>(LOCALE_DEPOT_KEY) {
+    tags.push(locale.to_owned());
+  }
+  if let Some(accept_language) = req.headers().get(ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok()) {
+    let mut ranges = accept_language
+      .split(',')
+      .filter_map(|range| {
+        let mut parts = range.split(';');
+        let tag = parts.next()?.trim();
+        let quality = parts
+          .filter_map(|param| param.trim().strip_prefix("q="))
+          .find_map(|quality| quality.trim().parse::<f32>().ok())
+          .unwrap_or(1.0);
+        (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag.to_owned(), quality))
+      })
+      .collect::<Vec<_>>();
+    ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    tags.extend(ranges.into_iter().map(|(tag, _)| tag));
+  }
+  let mut locales = vec![];
+  for tag in tags {
+    let primary = tag.split('-').next().unwrap_or_default().to_owned();
+    for locale in [tag, primary] {
+      if !locale.is_empty() && !locales.contains(&locale) {
+        locales.push(locale);
+      }
+    }
+  }
+  locales
+}
+
+/// Resolves the public text of a private error for the preferred locales.
+pub fn catalog_message(locales: &[String], status_code: StatusCode, error_code: Option<&str>) -> String {
+  let catalog = CATALOG.read().ok().and_then(|guard| guard.clone());
+  if let Some(catalog) = catalog {
+    for locale in locales {
+      if let Some(message) = catalog.message(locale, status_code, error_code) {
+        return message;
+      }
+    }
+  }
+  public_description(status_code).to_owned()
+}
+
+/// Resolves the public text of a private error for the request.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub fn localized_message(req: &Request, depot: &Depot, status_code: StatusCode, error_code: Option<&str>) -> String {
+  catalog_message(&request_locales(req, depot), status_code, error_code)
+}
+
+#[cfg(test)]
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+mod tests {
+  use super::*;
+  use salvo::http::HeaderValue;
+
+  fn locales(depot_locale: Option<&str>, accept_language: Option<&'static str>) -> Vec<String> {
+    let mut req = Request::new();
+    if let Some(accept_language) = accept_language {
+      req
+        .headers_mut()
+        .insert(ACCEPT_LANGUAGE, HeaderValue::from_static(accept_language));
+    }
+    let mut depot = Depot::new();
+    if let Some(locale) = depot_locale {
+      depot.insert(LOCALE_DEPOT_KEY, locale.to_owned());
+    }
+    request_locales(&req, &depot)
+  }
+
+  #[test]
+  fn request_locales_without_preferences_is_empty() {
+    assert!(locales(None, None).is_empty());
+    assert!(locales(None, Some("")).is_empty());
+  }
+
+  #[test]
+  fn request_locales_by_quality() {
+    assert_eq!(locales(None, Some("en;q=0.5, de, fr;q=0.8")), ["de", "fr", "en"]);
+    // Equal qualities keep the header order.
+    assert_eq!(locales(None, Some("fr, de;q=1.0, en")), ["fr", "de", "en"]);
+  }
+
+  #[test]
+  fn request_locales_skip_wildcard_and_zero_quality() {
+    assert_eq!(locales(None, Some("*, de;q=0, fr;q=0.0, en;q=0.1")), ["en"]);
+  }
+
+  #[test]
+  fn request_locales_fall_back_to_primary_language() {
+    assert_eq!(
+      locales(None, Some("ru-RU, en-US;q=0.5")),
+      ["ru-RU", "ru", "en-US", "en"]
+    );
+    assert_eq!(locales(None, Some("pt-BR, pt;q=0.9")), ["pt-BR", "pt"]);
+  }
+
+  #[test]
+  fn request_locales_prefer_depot_locale() {
+    assert_eq!(locales(Some("de-AT"), Some("en, de;q=0.5")), ["de-AT", "de", "en"]);
+    assert_eq!(locales(Some("fr"), None), ["fr"]);
+  }
+}
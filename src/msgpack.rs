@@ -0,0 +1,62 @@
+//! MessagePack encoding mode shared by the server writer, the parser and the client.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The way structs are encoded into MessagePack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MsgPackEncoding {
+  /// Structs are encoded as arrays of field values: compact, but adding or reordering fields breaks peers.
+  #[default]
+  Positional,
+  /// Structs are encoded as maps keyed by field names, so DTOs can evolve safely.
+  Named,
+}
+
+static NAMED_ENCODING: AtomicBool = AtomicBool::new(false);
+
+/// Sets the global encoding mode used by `MsgPack<T>`, `Negotiated<T>` and `MsgPackRequest::msgpack_body`.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// set_msgpack_encoding(MsgPackEncoding::Named);
+/// ```
+pub fn set_msgpack_encoding(encoding: MsgPackEncoding) {
+  NAMED_ENCODING.store(encoding == MsgPackEncoding::Named, Ordering::Relaxed);
+}
+
+/// Returns the global encoding mode.
+pub fn msgpack_encoding() -> MsgPackEncoding {
+  if NAMED_ENCODING.load(Ordering::Relaxed) {
+    MsgPackEncoding::Named
+  } else {
+    MsgPackEncoding::Positional
+  }
+}
+
+/// Serializes the value with the given encoding mode.
+pub fn to_vec_with<T: Serialize + ?Sized>(
+  value: &T,
+  encoding: MsgPackEncoding,
+) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+  match encoding {
+    MsgPackEncoding::Positional => rmp_serde::to_vec(value),
+    MsgPackEncoding::Named => rmp_serde::to_vec_named(value),
+  }
+}
+
+/// Serializes the value with the global encoding mode.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+  to_vec_with(value, msgpack_encoding())
+}
+
+/// Deserializes the value.
+///
+/// Both positional and named structs are accepted regardless of the global mode, so peers can switch modes one by one;
+/// with named encoding, missing `#[serde(default)]` fields and unknown fields are tolerated.
+pub fn from_slice<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, rmp_serde::decode::Error> {
+  rmp_serde::from_slice(bytes)
+}
@@ -2,42 +2,61 @@
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-pub use crate::brotli;
+pub use crate::{brotli, compression};
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-pub use crate::requests::MsgPackParser;
+pub use crate::compression::CompressionBuilder;
 
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub use salvo::prelude::CompressionLevel;
+
+#[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-pub use crate::results::MResult;
+pub use crate::requests::MsgPackParser;
+
+#[cfg(feature = "reqwest")]
+pub use crate::requests::MsgPackRequest;
+
+pub use crate::results::{CResult, MResult};
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-pub use crate::results::CResult;
+pub use crate::msgpack::{MsgPackEncoding, set_msgpack_encoding};
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-pub use crate::responses::{File, Html, Json, MsgPack, OK, Plain};
+pub use crate::responses::{
+  Accepted, Created, Decorate, Decorated, File, Html, Json, MsgPack, MsgPackEncoded, Negotiated, NoContent, OK, Plain,
+  StatusRegister, StatusWriter,
+};
 
 #[cfg(feature = "reqwest")]
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-pub use crate::responses::MsgPackResponse;
+pub use crate::responses::{CheckedResponse, MsgPackResponse};
 
+pub use crate::errors::{Consider, ConsiderDeclared, ErrorResponse, IntoErrorResponse, ResponseError};
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub use crate::errors::{ConsiderDepot, DepotExt, Errors};
+
+#[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-pub use crate::errors::{Consider, ErrorResponse};
+pub use crate::{Errors, declare_errors};
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
 pub use crate::errors::{CliError, ConsiderCli};
 
+pub use crate::errors::ErrorCode;
+
+pub use crate::messages::{EnglishCatalog, MessageCatalog, StaticCatalog, set_message_catalog};
+
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-pub use crate::{file_upload, html, json, msgpack, ok, plain};
+pub use crate::{accepted, created, file_upload, html, json, msgpack, negotiated, no_content, ok, plain};
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 pub use salvo::oapi::endpoint;
 
-#[cfg(feature = "salvo")]
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-pub use salvo::http::StatusCode;
+pub use http::StatusCode;
 
 pub use crate::tracing::Instrument as TracingInstrument;
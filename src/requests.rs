@@ -8,16 +8,171 @@ use crate::prelude::*;
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 use serde::Deserialize;
 
+#[cfg(feature = "reqwest")]
+use serde::Serialize;
+
+#[cfg(feature = "reqwest")]
+use crate::results::CResult;
+
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 use salvo::Request;
 
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use salvo::hyper::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use salvo::http::ParseError;
+
+#[cfg(feature = "decompression")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use std::io::Read;
+
+/// Request body inflated according to `Content-Encoding`.
+///
+/// It's stored in the request extensions, so deserialized values can borrow from it as from the raw payload.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[derive(Clone)]
+struct DecompressedPayload(Vec<u8>);
+
+/// Error for bodies exceeding the size limit.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+fn payload_too_large(text: &str) -> ErrorResponse {
+  ErrorResponse {
+    status_code: Some(StatusCode::PAYLOAD_TOO_LARGE),
+    error_text: text.into(),
+    public_error: true,
+    ..Default::default()
+  }
+}
+
+/// Checks whether the error chain contains the body length limit error.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+fn is_length_limit(error: &(dyn std::error::Error + 'static)) -> bool {
+  let mut error = Some(error);
+  while let Some(current) = error {
+    if current.is::<http_body_util::LengthLimitError>() {
+      return true;
+    }
+    error = current.source();
+  }
+  false
+}
+
+/// Reads the raw request body, rejecting bodies over `max_size` with 413.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+async fn limited_payload(req: &mut Request, max_size: usize) -> MResult<&[u8]> {
+  let content_length = req
+    .headers()
+    .get(CONTENT_LENGTH)
+    .and_then(|length| length.to_str().ok())
+    .and_then(|length| length.trim().parse::<u64>().ok());
+  if content_length.is_some_and(|length| length > max_size as u64) {
+    return Err(payload_too_large("Request body is too large."));
+  }
+  match req.payload_with_max_size(max_size).await {
+    Ok(payload) => Ok(payload.as_ref()),
+    Err(ParseError::Other(e)) if is_length_limit(e.as_ref()) => Err(payload_too_large("Request body is too large.")),
+    Err(e) => Err(e.into()),
+  }
+}
+
+/// Reads the request body, decompressing it according to `Content-Encoding`.
+///
+/// `max_size` limits both the compressed and the decompressed body size; bodies over it are rejected with 413.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+async fn decoded_payload(req: &mut Request, max_size: usize) -> MResult<&[u8]> {
+  let encoding = match req.headers().get(CONTENT_ENCODING) {
+    Some(encoding) => encoding.to_str().consider_400_pub()?.trim().to_ascii_lowercase(),
+    None => String::new(),
+  };
+  if encoding.is_empty() || encoding == "identity" {
+    return limited_payload(req, max_size).await;
+  }
+  let payload = limited_payload(req, max_size).await?.to_vec();
+  let decompressed = decompress(&encoding, &payload, max_size)?;
+  req.extensions_mut().insert(DecompressedPayload(decompressed));
+  let req: &Request = req;
+  Ok(
+    req
+      .extensions()
+      .get::<DecompressedPayload>()
+      .map(|payload| payload.0.as_slice())
+      .unwrap_or_default(),
+  )
+}
+
+/// Decompresses the body encoded with the `Content-Encoding` codings (`br`, `gzip`, `deflate`, `zstd`).
+#[cfg(feature = "decompression")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+fn decompress(encoding: &str, payload: &[u8], max_size: usize) -> MResult<Vec<u8>> {
+  let mut body = payload.to_vec();
+  // Codings are listed in the order they were applied.
+  for coding in encoding.split(',').map(str::trim).rev() {
+    let decoder: Box<dyn Read + '_> = match coding {
+      "" | "identity" => continue,
+      "br" => Box::new(brotli::Decompressor::new(body.as_slice(), 4096)),
+      "gzip" | "x-gzip" => Box::new(flate2::read::GzDecoder::new(body.as_slice())),
+      "deflate" => Box::new(flate2::read::ZlibDecoder::new(body.as_slice())),
+      "zstd" => Box::new(zstd::stream::read::Decoder::new(body.as_slice()).consider_400_pub()?),
+      _ => {
+        return Err(ErrorResponse {
+          status_code: Some(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+          error_text: format!("Unsupported content encoding `{}`.", coding),
+          public_error: true,
+          ..Default::default()
+        });
+      }
+    };
+    let mut decompressed = vec![];
+    decoder
+      .take(max_size as u64 + 1)
+      .read_to_end(&mut decompressed)
+      .consider(
+        Some(StatusCode::BAD_REQUEST),
+        Some("Failed to decompress request body."),
+        true,
+      )?;
+    if decompressed.len() > max_size {
+      return Err(payload_too_large("Decompressed request body is too large."));
+    }
+    body = decompressed;
+  }
+  Ok(body)
+}
+
+/// Rejects compressed bodies when the `decompression` feature is disabled.
+#[cfg(not(feature = "decompression"))]
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+fn decompress(encoding: &str, _payload: &[u8], _max_size: usize) -> MResult<Vec<u8>> {
+  Err(ErrorResponse {
+    status_code: Some(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+    error_text: format!("Unsupported content encoding `{}`.", encoding),
+    public_error: true,
+    ..Default::default()
+  })
+}
+
+/// Body parsers for `salvo` requests.
+///
+/// Bodies over the size limit are rejected with 413. Compressed bodies (`Content-Encoding: br`, `gzip`, `deflate`,
+/// `zstd`) are inflated only with the non-default `decompression` feature; without it they are rejected with 415.
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[allow(async_fn_in_trait)]
 pub trait MsgPackParser {
   async fn parse_msgpack<'de, T: Deserialize<'de>>(&'de mut self) -> MResult<T>;
   async fn parse_msgpack_with_max_size<'de, T: Deserialize<'de>>(&'de mut self, max_size: usize) -> MResult<T>;
+  async fn parse_any<'de, T: Deserialize<'de>>(&'de mut self) -> MResult<T>;
+  async fn parse_any_with_max_size<'de, T: Deserialize<'de>>(&'de mut self, max_size: usize) -> MResult<T>;
 }
 
 #[cfg(feature = "salvo")]
@@ -34,24 +189,181 @@ impl MsgPackParser for Request {
   /// Parse MessagePack body as type `T` from request with max size limit.
   #[inline]
   async fn parse_msgpack_with_max_size<'de, T: Deserialize<'de>>(&'de mut self, max_size: usize) -> MResult<T> {
-    let ctype = self.content_type();
-    if let Some(ctype) = ctype {
-      if ctype.subtype() == salvo::http::mime::MSGPACK {
-        let payload = self.payload_with_max_size(max_size).await?;
-        let payload = if payload.is_empty() {
-          "null".as_bytes()
-        } else {
-          payload.as_ref()
-        };
-        tracing::debug!("{:?}", payload);
-        return rmp_serde::from_slice::<T>(payload).consider(Some(StatusCode::BAD_REQUEST), None::<String>, true);
-      }
+    if let Some(ctype) = self.content_type()
+      && ctype.subtype() == salvo::http::mime::MSGPACK
+    {
+      let payload = decoded_payload(self, max_size).await?;
+      let payload = if payload.is_empty() { "null".as_bytes() } else { payload };
+      tracing::debug!("{:?}", payload);
+      return crate::msgpack::from_slice::<T>(payload).consider_400_pub();
     }
     Err(ErrorResponse {
       status_code: Some(StatusCode::BAD_REQUEST),
       error_text: "Bad content type, must be `application/msgpack`.".into(),
       original_text: None,
       public_error: true,
+      ..Default::default()
     })
   }
+
+  /// Parse JSON, MessagePack or form body as type `T` depending on `Content-Type` with default max size limit.
+  ///
+  /// Compressed bodies need the `decompression` feature, see `MsgPackParser`.
+  #[inline]
+  async fn parse_any<'de, T: Deserialize<'de>>(&'de mut self) -> MResult<T> {
+    self
+      .parse_any_with_max_size(salvo::http::request::global_secure_max_size())
+      .await
+  }
+
+  /// Parse JSON, MessagePack or form body as type `T` depending on `Content-Type` with max size limit.
+  #[inline]
+  async fn parse_any_with_max_size<'de, T: Deserialize<'de>>(&'de mut self, max_size: usize) -> MResult<T> {
+    match self.content_type() {
+      Some(ctype) if ctype.subtype() == salvo::http::mime::MSGPACK => self.parse_msgpack_with_max_size(max_size).await,
+      Some(ctype) if ctype.subtype() == salvo::http::mime::JSON || ctype.suffix() == Some(salvo::http::mime::JSON) => {
+        let payload = decoded_payload(self, max_size).await?;
+        let payload = if payload.is_empty() { "null".as_bytes() } else { payload };
+        serde_json::from_slice::<T>(payload).consider_400_pub()
+      }
+      Some(ctype) if ctype.subtype() == salvo::http::mime::WWW_FORM_URLENCODED => {
+        let payload = decoded_payload(self, max_size).await?;
+        serde_urlencoded::from_bytes::<T>(payload).consider_400_pub()
+      }
+      _ => Err(ErrorResponse {
+        status_code: Some(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+        error_text:
+          "Bad content type, must be `application/json`, `application/msgpack` or `application/x-www-form-urlencoded`."
+            .into(),
+        original_text: None,
+        public_error: true,
+        ..Default::default()
+      }),
+    }
+  }
+}
+
+/// Request-side MessagePack support for `reqwest` clients.
+#[cfg(feature = "reqwest")]
+pub trait MsgPackRequest: Sized {
+  fn msgpack_body<T: Serialize + ?Sized>(self, body: &T) -> CResult<Self>;
+}
+
+#[cfg(feature = "reqwest")]
+impl MsgPackRequest for reqwest::RequestBuilder {
+  /// Serializes the body as MessagePack and sets `Content-Type: application/msgpack`.
+  fn msgpack_body<T: Serialize + ?Sized>(self, body: &T) -> CResult<Self> {
+    let bytes = crate::msgpack::to_vec(body)?;
+    Ok(
+      self
+        .header(reqwest::header::CONTENT_TYPE, "application/msgpack")
+        .body(bytes),
+    )
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "decompression")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+mod tests {
+  use super::*;
+  use std::io::Write;
+
+  const DATA: &[u8] = b"{\"message\":\"hello, world\",\"padding\":\"0000000000000000000000000000000000000000\"}";
+
+  fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+  }
+
+  fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+  }
+
+  fn br(data: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![];
+    {
+      let mut encoder = brotli::CompressorWriter::new(&mut encoded, 4096, 5, 22);
+      encoder.write_all(data).unwrap();
+    }
+    encoded
+  }
+
+  fn zstd(data: &[u8]) -> Vec<u8> {
+    zstd::encode_all(data, 0).unwrap()
+  }
+
+  fn status(result: MResult<Vec<u8>>) -> Option<StatusCode> {
+    result.err().and_then(|e| e.status_code)
+  }
+
+  #[test]
+  fn decompresses_each_coding() {
+    for (coding, encoded) in [
+      ("gzip", gzip(DATA)),
+      ("x-gzip", gzip(DATA)),
+      ("deflate", deflate(DATA)),
+      ("br", br(DATA)),
+      ("zstd", zstd(DATA)),
+      ("identity", DATA.to_vec()),
+    ] {
+      assert_eq!(decompress(coding, &encoded, DATA.len()).unwrap(), DATA, "{}", coding);
+    }
+  }
+
+  #[test]
+  fn decompresses_stacked_codings_in_reverse_order() {
+    let encoded = br(&gzip(DATA));
+    assert_eq!(decompress("gzip, br", &encoded, DATA.len()).unwrap(), DATA);
+    let encoded = zstd(&deflate(DATA));
+    assert_eq!(decompress("deflate,identity,zstd", &encoded, DATA.len()).unwrap(), DATA);
+  }
+
+  #[test]
+  fn rejects_over_limit_bodies_with_413() {
+    let data = vec![b'0'; 64 * 1024];
+    for (coding, encoded) in [("gzip", gzip(&data)), ("br", br(&data)), ("zstd", zstd(&data))] {
+      assert!(encoded.len() < 1024, "{}", coding);
+      assert_eq!(
+        status(decompress(coding, &encoded, 1024)),
+        Some(StatusCode::PAYLOAD_TOO_LARGE),
+        "{}",
+        coding
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_over_limit_stacked_bodies_with_413() {
+    let data = vec![b'0'; 64 * 1024];
+    let encoded = br(&gzip(&data));
+    assert_eq!(
+      status(decompress("gzip, br", &encoded, 1024)),
+      Some(StatusCode::PAYLOAD_TOO_LARGE)
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_codings_with_415() {
+    assert_eq!(
+      status(decompress("compress", DATA, DATA.len())),
+      Some(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+    );
+    let encoded = gzip(DATA);
+    assert_eq!(
+      status(decompress("lzma, gzip", &encoded, DATA.len())),
+      Some(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+    );
+  }
+
+  #[test]
+  fn rejects_corrupted_bodies_with_400() {
+    assert_eq!(
+      status(decompress("gzip", DATA, DATA.len())),
+      Some(StatusCode::BAD_REQUEST)
+    );
+  }
 }
@@ -1,5 +1,6 @@
 //! Implementation of utilities for working with responses in `salvo` and `reqwest`.
 
+#[cfg(any(feature = "salvo", feature = "reqwest"))]
 use crate::prelude::*;
 
 #[cfg(feature = "salvo")]
@@ -8,7 +9,11 @@ use salvo::http::HeaderValue;
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-use salvo::hyper::header::CONTENT_TYPE;
+use salvo::hyper::header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE, HeaderName, LOCATION};
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+use salvo::http::cookie::Cookie;
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
@@ -26,11 +31,11 @@ use salvo::Writer as ServerResponseWriter;
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 use salvo::fs::NamedFile;
 
+#[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 use serde::Serialize;
 
 #[cfg(feature = "reqwest")]
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
 use serde::de::DeserializeOwned;
 
 /// Macro to define the function that called the response.
@@ -56,36 +61,98 @@ macro_rules! fn_name {
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 macro_rules! impl_oapi_endpoint_out {
   ($t:tt, $c:expr) => {
-    impl EndpointOutRegister for $t {
+    impl StatusRegister for $t {
       #[inline]
-      fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+      fn register_with_status(
+        status_code: &str,
+        description: &str,
+        components: &mut salvo::oapi::Components,
+        operation: &mut salvo::oapi::Operation,
+      ) {
         operation.responses.insert(
-          "200",
-          salvo::oapi::Response::new("Ok").add_content($c, String::to_schema(components)),
+          status_code,
+          salvo::oapi::Response::new(description).add_content($c, String::to_schema(components)),
         );
       }
     }
+
+    impl EndpointOutRegister for $t {
+      #[inline]
+      fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+        Self::register_with_status("200", "Ok", components, operation);
+      }
+    }
   };
 }
 
-/// Macro for automating `EndpointOutRegister` implementations (for template types)
+/// Macro for automating `EndpointOutRegister` implementations (for template types, documents the schema of `T`)
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 macro_rules! impl_oapi_endpoint_out_t {
   ($t:tt, $c:expr) => {
-    impl<T> EndpointOutRegister for $t<T> {
+    impl<T: ToSchema> StatusRegister for $t<T> {
       #[inline]
-      fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+      fn register_with_status(
+        status_code: &str,
+        description: &str,
+        components: &mut salvo::oapi::Components,
+        operation: &mut salvo::oapi::Operation,
+      ) {
         operation.responses.insert(
-          "200",
-          salvo::oapi::Response::new("Ok").add_content($c, String::to_schema(components)),
+          status_code,
+          salvo::oapi::Response::new(description).add_content($c, T::to_schema(components)),
         );
       }
     }
+
+    impl<T: ToSchema> EndpointOutRegister for $t<T> {
+      #[inline]
+      fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+        Self::register_with_status("200", "Ok", components, operation);
+      }
+    }
+  };
+}
+
+/// Response body that can be sent with any success status code.
+///
+/// Returned on its own, the body is sent with 200; `Created` and `Accepted` send it with 201 and 202,
+/// `Decorate::with_status` with any other success status.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[salvo::async_trait]
+pub trait StatusWriter: Send {
+  async fn write_with_status(self, status_code: StatusCode, req: &mut Request, depot: &mut Depot, res: &mut Response);
+}
+
+/// Response body that can be registered in OpenAPI under any success status code.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub trait StatusRegister {
+  fn register_with_status(
+    status_code: &str,
+    description: &str,
+    components: &mut salvo::oapi::Components,
+    operation: &mut salvo::oapi::Operation,
+  );
+}
+
+/// Macro for automating `Writer` implementations which send the body with 200.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+macro_rules! impl_writer_ok {
+  ($t:ty $(, $g:ident: $b:path)?) => {
+    #[salvo::async_trait]
+    impl$(<$g: $b + Send>)? ServerResponseWriter for $t {
+      #[inline]
+      async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+        self.write_with_status(StatusCode::OK, req, depot, res).await;
+      }
+    }
   };
 }
 
-/// Sends 200 without data.
+/// Sends an empty body with 200 or the status of the wrapping response (see `StatusWriter`).
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 pub struct OK(pub &'static str);
@@ -106,15 +173,25 @@ macro_rules! ok {
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[salvo::async_trait]
-impl ServerResponseWriter for OK {
-  async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
-    res.status_code(StatusCode::OK);
+impl StatusWriter for OK {
+  async fn write_with_status(
+    self,
+    status_code: StatusCode,
+    _req: &mut Request,
+    _depot: &mut Depot,
+    res: &mut Response,
+  ) {
+    res.status_code(status_code);
     res.render("");
-    tracing::debug!("[{}] => Received and sent result 200", self.0);
+    tracing::debug!("[{}] => Received and sent result {}", self.0, status_code.as_u16());
   }
 }
 
-/// Sends 200 and plain text.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_writer_ok!(OK);
+
+/// Sends plain text with 200 or the status of the wrapping response (see `StatusWriter`).
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[derive(Debug)]
@@ -139,15 +216,30 @@ macro_rules! plain {
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[salvo::async_trait]
-impl ServerResponseWriter for Plain {
-  async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
-    res.status_code(StatusCode::OK);
+impl StatusWriter for Plain {
+  async fn write_with_status(
+    self,
+    status_code: StatusCode,
+    _req: &mut Request,
+    _depot: &mut Depot,
+    res: &mut Response,
+  ) {
+    res.status_code(status_code);
     res.render(&self.0);
-    tracing::debug!("[{}] => Received and sent result 200 with text: {}", self.1, self.0);
+    tracing::debug!(
+      "[{}] => Received and sent result {} with text: {}",
+      self.1,
+      status_code.as_u16(),
+      self.0
+    );
   }
 }
 
-/// Sends 200 and HTML.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_writer_ok!(Plain);
+
+/// Sends HTML with 200 or the status of the wrapping response (see `StatusWriter`).
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[derive(Debug)]
@@ -172,15 +264,29 @@ macro_rules! html {
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[salvo::async_trait]
-impl ServerResponseWriter for Html {
-  async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
-    res.status_code(StatusCode::OK);
+impl StatusWriter for Html {
+  async fn write_with_status(
+    self,
+    status_code: StatusCode,
+    _req: &mut Request,
+    _depot: &mut Depot,
+    res: &mut Response,
+  ) {
+    res.status_code(status_code);
     res.render(salvo::writing::Text::Html(&self.0));
-    tracing::debug!("[{}] => Received and sent result 200 with HTML", self.1);
+    tracing::debug!(
+      "[{}] => Received and sent result {} with HTML",
+      self.1,
+      status_code.as_u16()
+    );
   }
 }
 
-/// Sends 200 and file.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_writer_ok!(Html);
+
+/// Sends file with 200 or the status of the wrapping response (see `StatusWriter`).
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[derive(Debug)]
@@ -196,7 +302,6 @@ impl_oapi_endpoint_out!(File, "application/octet-stream");
 ///
 /// ```rust
 /// use cc_utils::prelude::*;
-/// use salvo::prelude::*;
 ///
 /// pub async fn some_endpoint() -> MResult<File> {
 ///   file_upload!("filepath".to_string(), "Normal file name".to_string())
@@ -218,19 +323,28 @@ macro_rules! file_upload {
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[salvo::async_trait]
-impl ServerResponseWriter for File {
-  async fn write(self, req: &mut Request, _depot: &mut Depot, res: &mut Response) {
-    res.status_code(StatusCode::OK);
+impl StatusWriter for File {
+  async fn write_with_status(self, status_code: StatusCode, req: &mut Request, _depot: &mut Depot, res: &mut Response) {
+    res.status_code(status_code);
     NamedFile::builder(&self.0)
       .attached_name(&self.1)
       .use_last_modified(true)
       .send(req.headers(), res)
       .await;
-    tracing::debug!("[{}] => Received and sent result 200 with file {}", self.2, self.1);
+    tracing::debug!(
+      "[{}] => Received and sent result {} with file {}",
+      self.2,
+      status_code.as_u16(),
+      self.1
+    );
   }
 }
 
-/// Sends 200 and JSON.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_writer_ok!(File);
+
+/// Sends JSON with 200 or the status of the wrapping response (see `StatusWriter`).
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[derive(Debug)]
@@ -255,9 +369,9 @@ macro_rules! json {
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[salvo::async_trait]
-impl<T: Serialize + Send> ServerResponseWriter for Json<T> {
-  async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
-    res.status_code(StatusCode::OK);
+impl<T: Serialize + Send> StatusWriter for Json<T> {
+  async fn write_with_status(self, status_code: StatusCode, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    res.status_code(status_code);
     match serde_json::to_string(&self.0) {
       Ok(s) => {
         res.headers_mut().insert(
@@ -266,7 +380,11 @@ impl<T: Serialize + Send> ServerResponseWriter for Json<T> {
         );
         tracing::debug!("[{}] => Sending JSON: {:?}", self.1, s.as_str());
         res.write_body(s).ok();
-        tracing::debug!("[{}] => Received and sent result 200 with JSON", self.1);
+        tracing::debug!(
+          "[{}] => Received and sent result {} with JSON",
+          self.1,
+          status_code.as_u16()
+        );
       }
       Err(e) => {
         tracing::error!("[{}] => Failed to serialize data: {:?}", e, self.1);
@@ -280,7 +398,14 @@ impl<T: Serialize + Send> ServerResponseWriter for Json<T> {
   }
 }
 
-/// Sends 200 and MsgPack.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_writer_ok!(Json<T>, T: Serialize);
+
+/// Sends MsgPack with 200 or the status of the wrapping response (see `StatusWriter`).
+///
+/// Structs are encoded according to the global `MsgPackEncoding` (see `cc_utils::msgpack`); use `with_encoding` or
+/// `msgpack!(data, encoding)` to override it for a single response.
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[derive(Debug)]
@@ -290,6 +415,42 @@ pub struct MsgPack<T>(pub T, pub &'static str);
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 impl_oapi_endpoint_out_t!(MsgPack, "application/msgpack");
 
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<T> MsgPack<T> {
+  /// Overrides the global `MsgPackEncoding` for this response.
+  ///
+  /// Usage:
+  ///
+  /// ```rust
+  /// use cc_utils::prelude::*;
+  /// use serde::Serialize;
+  ///
+  /// #[derive(Serialize)]
+  /// struct Stats {
+  ///   visits: u64,
+  /// }
+  ///
+  /// pub async fn stats() -> MResult<MsgPackEncoded<Stats>> {
+  ///   msgpack!(Stats { visits: 42 }, MsgPackEncoding::Named)
+  /// }
+  /// ```
+  pub fn with_encoding(self, encoding: MsgPackEncoding) -> MsgPackEncoded<T> {
+    MsgPackEncoded(self.0, self.1, encoding)
+  }
+}
+
+/// Sends MsgPack encoded with the given `MsgPackEncoding`, with 200 or the status of the wrapping response
+/// (see `StatusWriter`).
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[derive(Debug)]
+pub struct MsgPackEncoded<T>(pub T, pub &'static str, pub MsgPackEncoding);
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_oapi_endpoint_out_t!(MsgPackEncoded, "application/msgpack");
+
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[macro_export]
@@ -300,15 +461,40 @@ macro_rules! msgpack {
       $crate::fn_name!(),
     ))
   };
+  ($msgpack_data:expr, $encoding:expr) => {
+    Ok::<cc_utils::responses::MsgPackEncoded<_>, cc_utils::errors::ErrorResponse>(cc_utils::responses::MsgPackEncoded(
+      $msgpack_data,
+      $crate::fn_name!(),
+      $encoding,
+    ))
+  };
 }
 
 #[cfg(feature = "salvo")]
 #[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
 #[salvo::async_trait]
-impl<T: Serialize + Send> ServerResponseWriter for MsgPack<T> {
-  async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
-    res.status_code(StatusCode::OK);
-    match rmp_serde::to_vec(&self.0) {
+impl<T: Serialize + Send> StatusWriter for MsgPack<T> {
+  #[inline]
+  async fn write_with_status(self, status_code: StatusCode, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let encoding = crate::msgpack::msgpack_encoding();
+    self
+      .with_encoding(encoding)
+      .write_with_status(status_code, req, depot, res)
+      .await;
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_writer_ok!(MsgPack<T>, T: Serialize);
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[salvo::async_trait]
+impl<T: Serialize + Send> StatusWriter for MsgPackEncoded<T> {
+  async fn write_with_status(self, status_code: StatusCode, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    res.status_code(status_code);
+    match crate::msgpack::to_vec_with(&self.0, self.2) {
       Ok(bytes) => {
         res.headers_mut().insert(
           CONTENT_TYPE,
@@ -316,7 +502,11 @@ impl<T: Serialize + Send> ServerResponseWriter for MsgPack<T> {
         );
         tracing::debug!("[{}] => Sending bytes: {:?}", self.1, bytes);
         res.write_body(bytes).ok();
-        tracing::debug!("[{}] => Received and sent result 200 with MsgPack", self.1);
+        tracing::debug!(
+          "[{}] => Received and sent result {} with MsgPack",
+          self.1,
+          status_code.as_u16()
+        );
       }
       Err(e) => {
         tracing::error!("[{}] => Failed to serialize data: {:?}", e, self.1);
@@ -330,18 +520,567 @@ impl<T: Serialize + Send> ServerResponseWriter for MsgPack<T> {
   }
 }
 
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_writer_ok!(MsgPackEncoded<T>, T: Serialize);
+
+/// Picks the most preferred of `offered` media types according to the `Accept` header value.
+///
+/// Missing or empty `Accept` means the first offered type; `None` means nothing is acceptable.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub(crate) fn negotiate(accept: Option<&str>, offered: &[&'static str]) -> Option<&'static str> {
+  let Some(accept) = accept.filter(|accept| !accept.trim().is_empty()) else {
+    return offered.first().copied();
+  };
+  let ranges = accept
+    .split(',')
+    .map(|range| {
+      let mut parts = range.split(';');
+      let media = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+      let quality = parts
+        .find_map(|param| {
+          param
+            .trim()
+            .strip_prefix("q=")
+            .and_then(|q| q.trim().parse::<f32>().ok())
+        })
+        .unwrap_or(1.0);
+      (media, quality)
+    })
+    .collect::<Vec<_>>();
+
+  let mut best: Option<(&'static str, f32)> = None;
+  for offer in offered {
+    // The most specific matching media range defines the quality of the offered type.
+    let quality = ranges
+      .iter()
+      .filter_map(|(media, quality)| {
+        if media == offer {
+          Some((2, *quality))
+        } else if media == "*/*" {
+          Some((0, *quality))
+        } else if media
+          .strip_suffix('*')
+          .is_some_and(|prefix| prefix.ends_with('/') && offer.starts_with(prefix))
+        {
+          Some((1, *quality))
+        } else {
+          None
+        }
+      })
+      .max_by_key(|(specificity, _)| *specificity)
+      .map(|(_, quality)| quality)
+      .unwrap_or(0.0);
+    if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+      best = Some((*offer, quality));
+    }
+  }
+  best.map(|(offer, _)| offer)
+}
+
+/// Sends JSON or MsgPack, depending on the request's `Accept` header, with 200 or the status of the wrapping response
+/// (see `StatusWriter`).
+///
+/// Responds with 406 if neither `application/json` nor `application/msgpack` is acceptable.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[derive(Debug)]
+pub struct Negotiated<T>(pub T, pub &'static str);
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<T: ToSchema> StatusRegister for Negotiated<T> {
+  #[inline]
+  fn register_with_status(
+    status_code: &str,
+    description: &str,
+    components: &mut salvo::oapi::Components,
+    operation: &mut salvo::oapi::Operation,
+  ) {
+    operation.responses.insert(
+      status_code,
+      salvo::oapi::Response::new(description)
+        .add_content("application/json", T::to_schema(components))
+        .add_content("application/msgpack", T::to_schema(components)),
+    );
+    crate::errors::register_error_status(components, operation, "406", "Not acceptable");
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<T: ToSchema> EndpointOutRegister for Negotiated<T> {
+  #[inline]
+  fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+    Self::register_with_status("200", "Ok", components, operation);
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[macro_export]
+macro_rules! negotiated {
+  ($data:expr) => {
+    Ok::<cc_utils::responses::Negotiated<_>, cc_utils::errors::ErrorResponse>(cc_utils::responses::Negotiated(
+      $data,
+      $crate::fn_name!(),
+    ))
+  };
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[salvo::async_trait]
+impl<T: Serialize + Send> StatusWriter for Negotiated<T> {
+  async fn write_with_status(self, status_code: StatusCode, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let accept = req.headers().get(ACCEPT).and_then(|value| value.to_str().ok());
+    match negotiate(accept, &["application/json", "application/msgpack"]) {
+      Some("application/msgpack") => {
+        MsgPack(self.0, self.1)
+          .write_with_status(status_code, req, depot, res)
+          .await
+      }
+      Some(_) => {
+        Json(self.0, self.1)
+          .write_with_status(status_code, req, depot, res)
+          .await
+      }
+      None => {
+        tracing::debug!("[{}] => No acceptable response format, sending 406", self.1);
+        ErrorResponse::from("Only `application/json` and `application/msgpack` responses are available.")
+          .with_406_pub()
+          .build()
+          .write(req, depot, res)
+          .await;
+      }
+    }
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl_writer_ok!(Negotiated<T>, T: Serialize);
+
+/// Sends 201 with the wrapped body (`Json`, `MsgPack`, `Negotiated`, ...) and optional `Location` header.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[derive(Debug)]
+pub struct Created<B>(pub B, pub Option<String>);
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<B: StatusRegister> EndpointOutRegister for Created<B> {
+  #[inline]
+  fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+    B::register_with_status("201", "Created", components, operation);
+  }
+}
+
+/// Created response.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// pub async fn some_endpoint() -> MResult<Created<Json<u64>>> {
+///   created!(json => 42, "/items/42")
+/// }
+/// ```
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[macro_export]
+macro_rules! created {
+  (json => $data:expr) => {
+    $crate::created!(@wrap Json, $data, None)
+  };
+  (json => $data:expr, $location:expr) => {
+    $crate::created!(@wrap Json, $data, Some($location.into()))
+  };
+  (msgpack => $data:expr) => {
+    $crate::created!(@wrap MsgPack, $data, None)
+  };
+  (msgpack => $data:expr, $location:expr) => {
+    $crate::created!(@wrap MsgPack, $data, Some($location.into()))
+  };
+  (negotiated => $data:expr) => {
+    $crate::created!(@wrap Negotiated, $data, None)
+  };
+  (negotiated => $data:expr, $location:expr) => {
+    $crate::created!(@wrap Negotiated, $data, Some($location.into()))
+  };
+  (@wrap $body:ident, $data:expr, $location:expr) => {
+    Ok::<cc_utils::responses::Created<cc_utils::responses::$body<_>>, cc_utils::errors::ErrorResponse>(
+      cc_utils::responses::Created(cc_utils::responses::$body($data, $crate::fn_name!()), $location),
+    )
+  };
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[salvo::async_trait]
+impl<B: StatusWriter> ServerResponseWriter for Created<B> {
+  async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    if let Some(location) = self.1 {
+      match HeaderValue::from_str(&location) {
+        Ok(location) => {
+          res.headers_mut().insert(LOCATION, location);
+        }
+        Err(e) => tracing::error!("Invalid `Location` header value {:?}: {:?}", location, e),
+      }
+    }
+    self.0.write_with_status(StatusCode::CREATED, req, depot, res).await;
+  }
+}
+
+/// Sends 202 with the wrapped body (`Json`, `MsgPack`, `Negotiated`, ...).
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[derive(Debug)]
+pub struct Accepted<B>(pub B);
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<B: StatusRegister> EndpointOutRegister for Accepted<B> {
+  #[inline]
+  fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+    B::register_with_status("202", "Accepted", components, operation);
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[macro_export]
+macro_rules! accepted {
+  (json => $data:expr) => {
+    $crate::accepted!(@wrap Json, $data)
+  };
+  (msgpack => $data:expr) => {
+    $crate::accepted!(@wrap MsgPack, $data)
+  };
+  (negotiated => $data:expr) => {
+    $crate::accepted!(@wrap Negotiated, $data)
+  };
+  (@wrap $body:ident, $data:expr) => {
+    Ok::<cc_utils::responses::Accepted<cc_utils::responses::$body<_>>, cc_utils::errors::ErrorResponse>(
+      cc_utils::responses::Accepted(cc_utils::responses::$body($data, $crate::fn_name!())),
+    )
+  };
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[salvo::async_trait]
+impl<B: StatusWriter> ServerResponseWriter for Accepted<B> {
+  async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    self.0.write_with_status(StatusCode::ACCEPTED, req, depot, res).await;
+  }
+}
+
+/// Sends 204 without body.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub struct NoContent(pub &'static str);
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl EndpointOutRegister for NoContent {
+  #[inline]
+  fn register(_components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+    operation
+      .responses
+      .insert("204", salvo::oapi::Response::new("No content"));
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[macro_export]
+macro_rules! no_content {
+  () => {
+    Ok::<cc_utils::responses::NoContent, cc_utils::errors::ErrorResponse>(cc_utils::responses::NoContent(
+      $crate::fn_name!(),
+    ))
+  };
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[salvo::async_trait]
+impl ServerResponseWriter for NoContent {
+  async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
+    res.status_code(StatusCode::NO_CONTENT);
+    tracing::debug!("[{}] => Received and sent result 204", self.0);
+  }
+}
+
+/// Response decorated with extra headers, cookies and status code override.
+///
+/// Usage:
+///
+/// ```rust
+/// use cc_utils::prelude::*;
+///
+/// pub async fn some_endpoint() -> MResult<Decorated<Json<u64>>> {
+///   json!(42).with_cache_control("no-store").with_header("x-request-source", "cache")
+/// }
+/// ```
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[derive(Debug)]
+pub struct Decorated<B> {
+  pub body: B,
+  pub status_code: Option<StatusCode>,
+  pub headers: Vec<(String, String)>,
+  pub cookies: Vec<Cookie<'static>>,
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<B> Decorated<B> {
+  /// Wraps the response without any decorations.
+  pub fn new(body: B) -> Self {
+    Self {
+      body,
+      status_code: None,
+      headers: vec![],
+      cookies: vec![],
+    }
+  }
+
+  /// Adds header (overrides the header set by the wrapped response).
+  pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+    self.headers.push((name.into(), value.into()));
+    self
+  }
+
+  /// Adds `Cache-Control` header.
+  pub fn with_cache_control(self, value: impl Into<String>) -> Self {
+    self.with_header(CACHE_CONTROL.as_str(), value)
+  }
+
+  /// Adds cookie.
+  pub fn with_cookie(mut self, cookie: Cookie<'static>) -> Self {
+    self.cookies.push(cookie);
+    self
+  }
+
+  /// Overrides success status code of the wrapped response.
+  ///
+  /// The status is chosen at runtime, so OpenAPI still documents the status of the wrapped response; describe the
+  /// override with `#[endpoint(responses(...))]` on the handler.
+  pub fn with_status(mut self, status_code: StatusCode) -> Self {
+    self.status_code = Some(status_code);
+    self
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<B: EndpointOutRegister> EndpointOutRegister for Decorated<B> {
+  /// Documents the wrapped response only: the status override isn't known until the handler runs.
+  #[inline]
+  fn register(components: &mut salvo::oapi::Components, operation: &mut salvo::oapi::Operation) {
+    B::register(components, operation);
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+#[salvo::async_trait]
+impl<B: ServerResponseWriter + Send> ServerResponseWriter for Decorated<B> {
+  async fn write(self, req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    self.body.write(req, depot, res).await;
+    // Errors of the wrapped response must not be hidden by the override.
+    if let Some(status_code) = self.status_code
+      && res.status_code.is_none_or(|current| current.is_success())
+    {
+      res.status_code(status_code);
+      tracing::debug!("Response status code overridden with {}", status_code.as_u16());
+    }
+    for (name, value) in self.headers {
+      match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(&value)) {
+        (Ok(header_name), Ok(header_value)) => {
+          res.headers_mut().insert(header_name, header_value);
+        }
+        _ => tracing::error!("Invalid header {:?}: {:?}", name, value),
+      }
+    }
+    for cookie in self.cookies {
+      res.add_cookie(cookie);
+    }
+  }
+}
+
+/// Decorations for `json!`, `msgpack!`, `created!` and other responses.
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+pub trait Decorate: Sized {
+  type Body;
+
+  /// Wraps the response into `Decorated`.
+  fn decorated(self) -> MResult<Decorated<Self::Body>>;
+
+  /// Adds header (overrides the header set by the wrapped response).
+  fn with_header(self, name: impl Into<String>, value: impl Into<String>) -> MResult<Decorated<Self::Body>> {
+    self.decorated().map(|r| r.with_header(name, value))
+  }
+
+  /// Adds `Cache-Control` header.
+  fn with_cache_control(self, value: impl Into<String>) -> MResult<Decorated<Self::Body>> {
+    self.decorated().map(|r| r.with_cache_control(value))
+  }
+
+  /// Adds cookie.
+  fn with_cookie(self, cookie: Cookie<'static>) -> MResult<Decorated<Self::Body>> {
+    self.decorated().map(|r| r.with_cookie(cookie))
+  }
+
+  /// Overrides success status code of the response (not reflected in OpenAPI, see `Decorated::with_status`).
+  fn with_status(self, status_code: StatusCode) -> MResult<Decorated<Self::Body>> {
+    self.decorated().map(|r| r.with_status(status_code))
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<B: StatusWriter> Decorate for MResult<B> {
+  type Body = B;
+
+  fn decorated(self) -> MResult<Decorated<B>> {
+    self.map(Decorated::new)
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<B> Decorate for MResult<Decorated<B>> {
+  type Body = B;
+
+  fn decorated(self) -> MResult<Decorated<B>> {
+    self
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<B> Decorate for MResult<Created<B>> {
+  type Body = Created<B>;
+
+  fn decorated(self) -> MResult<Decorated<Created<B>>> {
+    self.map(Decorated::new)
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl<B> Decorate for MResult<Accepted<B>> {
+  type Body = Accepted<B>;
+
+  fn decorated(self) -> MResult<Decorated<Accepted<B>>> {
+    self.map(Decorated::new)
+  }
+}
+
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+impl Decorate for MResult<NoContent> {
+  type Body = NoContent;
+
+  fn decorated(self) -> MResult<Decorated<NoContent>> {
+    self.map(Decorated::new)
+  }
+}
+
+/// Client-side response handler which turns server errors into `CliError`.
+#[cfg(feature = "reqwest")]
+#[allow(async_fn_in_trait)]
+pub trait CheckedResponse: Sized {
+  async fn checked(self) -> CResult<Self>;
+}
+
+#[cfg(feature = "reqwest")]
+impl CheckedResponse for reqwest::Response {
+  /// Returns the response as is on success, otherwise decodes the server's `ErrorResponse` into `CliError`.
+  async fn checked(self) -> CResult<Self> {
+    if self.status().is_success() {
+      Ok(self)
+    } else {
+      Err(CliError::from_response(self).await)
+    }
+  }
+}
+
 #[cfg(feature = "reqwest")]
 #[allow(async_fn_in_trait)]
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
 pub trait MsgPackResponse {
   async fn msgpack<T: DeserializeOwned>(self) -> CResult<T>;
 }
 
 #[cfg(feature = "reqwest")]
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
 impl MsgPackResponse for reqwest::Response {
   async fn msgpack<T: DeserializeOwned>(self) -> CResult<T> {
-    let full = self.bytes().await?;
-    rmp_serde::from_slice(&full).consider_cli(None)
+    let full = self.checked().await?.bytes().await?;
+    crate::msgpack::from_slice(&full).consider_cli(None)
+  }
+}
+
+#[cfg(test)]
+#[cfg(feature = "salvo")]
+#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+mod tests {
+  use super::*;
+
+  const OFFERED: &[&str] = &["application/json", "application/msgpack"];
+
+  #[test]
+  fn negotiate_without_accept_picks_first_offered() {
+    assert_eq!(negotiate(None, OFFERED), Some("application/json"));
+    assert_eq!(negotiate(Some("  "), OFFERED), Some("application/json"));
+  }
+
+  #[test]
+  fn negotiate_by_quality() {
+    assert_eq!(
+      negotiate(Some("application/json;q=0.5, application/msgpack"), OFFERED),
+      Some("application/msgpack")
+    );
+    assert_eq!(
+      negotiate(Some("application/msgpack; q=0.4, application/json; q=0.9"), OFFERED),
+      Some("application/json")
+    );
+    assert_eq!(
+      negotiate(Some("Application/MsgPack"), OFFERED),
+      Some("application/msgpack")
+    );
+  }
+
+  #[test]
+  fn negotiate_wildcards() {
+    assert_eq!(negotiate(Some("*/*"), OFFERED), Some("application/json"));
+    assert_eq!(negotiate(Some("application/*"), OFFERED), Some("application/json"));
+    assert_eq!(
+      negotiate(Some("application/*;q=0.2, application/msgpack"), OFFERED),
+      Some("application/msgpack")
+    );
+    assert_eq!(negotiate(Some("text/*"), OFFERED), None);
+  }
+
+  #[test]
+  fn negotiate_excludes_zero_quality() {
+    assert_eq!(
+      negotiate(Some("application/json;q=0, */*"), OFFERED),
+      Some("application/msgpack")
+    );
+    assert_eq!(
+      negotiate(Some("application/json;q=0, application/msgpack;q=0"), OFFERED),
+      None
+    );
+  }
+
+  #[test]
+  fn negotiate_nothing_acceptable_means_406() {
+    assert_eq!(negotiate(Some("text/html, image/*"), OFFERED), None);
   }
 }
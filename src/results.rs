@@ -1,13 +1,12 @@
-//! Result types for `ErrorResponse` (`salvo`) and `CliError` (`reqwest`) errors.
+//! Result types for `ErrorResponse` (server) and `CliError` (client) errors.
 
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-use crate::errors::ErrorResponse;
+use crate::errors::{CliError, ErrorResponse};
 
-#[cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
-pub type MResult<T> = Result<T, ErrorResponse>;
+/// Result type shared by server handlers and target-neutral code (DTOs, validation) on both native and wasm targets.
+///
+/// Errors convert into `CliError` with `?` or `consider_cli` at the client edge and are written by `salvo` at the
+/// server edge. The error type can be replaced, e.g. with `Errors![404, 409]`.
+pub type MResult<T, E = ErrorResponse> = Result<T, E>;
 
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
-use crate::errors::CliError;
-
-#[cfg(any(target_arch = "wasm32", target_arch = "wasm64"))]
+/// Result type for client errors, available on both native and wasm targets.
 pub type CResult<T> = Result<T, CliError>;
@@ -0,0 +1,94 @@
+//! Behaviour of `#[derive(IntoErrorResponse)]` types converted into `ErrorResponse`.
+
+#![cfg(feature = "derive")]
+#![cfg(not(any(target_arch = "wasm32", target_arch = "wasm64")))]
+
+use cc_utils::prelude::*;
+
+#[derive(Debug, thiserror::Error, IntoErrorResponse)]
+#[response(code = "user_error")]
+enum UserError {
+  #[error("user {0} not found")]
+  #[response(status = 404, public, code = "user_not_found")]
+  NotFound(i64),
+  #[error("user {name} already exists")]
+  #[response(status = 409, message = "User already exists.")]
+  Exists { name: String },
+  #[error("connection to db:5432 refused")]
+  Database,
+}
+
+#[derive(Debug, thiserror::Error, IntoErrorResponse)]
+#[error("token expired")]
+#[response(status = 401, public)]
+struct TokenExpired;
+
+fn fail(error: UserError) -> MResult<()> {
+  Err(error)?;
+  Ok(())
+}
+
+#[test]
+fn question_mark_applies_variant_declaration() {
+  let error = fail(UserError::NotFound(7)).unwrap_err();
+  assert_eq!(error.status_code, Some(StatusCode::NOT_FOUND));
+  assert!(error.public_error);
+  assert_eq!(error.error_code.as_deref(), Some("user_not_found"));
+  assert_eq!(error.public_text(&[]), "user 7 not found");
+}
+
+#[test]
+fn question_mark_applies_declared_message() {
+  let error = fail(UserError::Exists { name: "bob".into() }).unwrap_err();
+  assert_eq!(error.status_code, Some(StatusCode::CONFLICT));
+  assert_eq!(error.public_text(&[]), "User already exists.");
+  assert_eq!(error.original_text.as_deref(), Some("user bob already exists"));
+  assert_eq!(error.error_code.as_deref(), Some("user_error"));
+}
+
+#[test]
+fn undeclared_variant_is_private_500() {
+  let error = fail(UserError::Database).unwrap_err();
+  assert_eq!(error.status_code, Some(StatusCode::INTERNAL_SERVER_ERROR));
+  assert!(!error.public_error);
+  assert!(!error.public_text(&[]).contains("db:5432"));
+  assert_eq!(error.error_code.as_deref(), Some("user_error"));
+}
+
+#[test]
+fn struct_declaration_applies_through_from() {
+  let error = ErrorResponse::from(TokenExpired);
+  assert_eq!(error.status_code, Some(StatusCode::UNAUTHORIZED));
+  assert_eq!(error.public_text(&[]), "token expired");
+}
+
+#[test]
+fn question_mark_matches_consider_declared() {
+  let declared = Err::<(), _>(UserError::NotFound(7)).consider_declared().unwrap_err();
+  let converted = fail(UserError::NotFound(7)).unwrap_err();
+  assert_eq!(declared.status_code, converted.status_code);
+  assert_eq!(declared.public_text(&[]), converted.public_text(&[]));
+  assert_eq!(declared.error_code, converted.error_code);
+}
+
+#[test]
+fn question_mark_into_errors_applies_declaration() {
+  fn fail_declared() -> MResult<(), Errors![404, 409]> {
+    Err(UserError::NotFound(7))?;
+    Ok(())
+  }
+  let error = fail_declared().unwrap_err().0;
+  assert_eq!(error.status_code, Some(StatusCode::NOT_FOUND));
+}
+
+#[test]
+fn declared_statuses_group_codes() {
+  assert_eq!(
+    UserError::declared_statuses(),
+    vec![
+      (StatusCode::NOT_FOUND, vec!["user_not_found"]),
+      (StatusCode::CONFLICT, vec!["user_error"]),
+      (StatusCode::INTERNAL_SERVER_ERROR, vec!["user_error"]),
+    ]
+  );
+}